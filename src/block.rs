@@ -0,0 +1,358 @@
+use std::collections::HashSet;
+
+use geo::{Coord, LineString, Point, Polygon};
+
+use super::{config::EPSILON, graph::RoadGraph, math::length, segment::Segment, zoning::Zone};
+
+/// A directed edge along a face, as (from node, to node, segment id).
+type HalfEdge = (usize, usize, usize);
+
+/// An area enclosed by roads, with no road running through it.
+#[derive(Debug, Clone)]
+pub struct Block {
+    /// Outline running counter-clockwise along the centre lines of the
+    /// surrounding roads, with a hole for every loop of road that hangs
+    /// inside the block by a single road.
+    pub polygon: Polygon<f64>,
+    pub area: f64,
+    pub perimeter: f64,
+    /// Ids of the segments along the outline, in order, followed by those
+    /// around each hole.
+    pub segments: Vec<usize>,
+    /// Land use, once assigned by [`Zoning`](crate::zoning::Zoning).
+    pub zone: Option<Zone>,
+}
+
+/// Finds the blocks of a network as the bounded faces of its planar graph.
+///
+/// Dead ends are pruned first since they enclose nothing, then every face is
+/// traced by always taking the next road clockwise at each node, which keeps
+/// the face on the left. The unbounded outer face comes out clockwise and is
+/// dropped along with degenerate faces. Roads reaching into a block from its
+/// outline to an inner loop are left out of it.
+pub fn extract_blocks(segments: &[Segment]) -> Vec<Block> {
+    let graph = RoadGraph::from_segments(segments);
+
+    // unique edges between distinct nodes, as (start, end, segment id)
+    let mut seen = HashSet::new();
+    let mut edges: Vec<(usize, usize, usize)> = Vec::new();
+    for edge in graph.edges.iter() {
+        let key = (edge.start.min(edge.end), edge.start.max(edge.end));
+        if edge.start != edge.end && seen.insert(key) {
+            edges.push((edge.start, edge.end, edge.segment_id));
+        }
+    }
+
+    // strip dead ends until every remaining node closes a loop
+    let mut alive = vec![true; edges.len()];
+    let mut degree = vec![0usize; graph.nodes.len()];
+    let mut node_edges: Vec<Vec<usize>> = vec![Vec::new(); graph.nodes.len()];
+    for (index, (start, end, _)) in edges.iter().enumerate() {
+        degree[*start] += 1;
+        degree[*end] += 1;
+        node_edges[*start].push(index);
+        node_edges[*end].push(index);
+    }
+
+    let mut dead_ends: Vec<usize> = (0..degree.len()).filter(|n| degree[*n] == 1).collect();
+    while let Some(node) = dead_ends.pop() {
+        let Some(&index) = node_edges[node].iter().find(|index| alive[**index]) else {
+            continue;
+        };
+        alive[index] = false;
+
+        let (start, end, _) = edges[index];
+        for end_node in [start, end] {
+            degree[end_node] -= 1;
+            if degree[end_node] == 1 {
+                dead_ends.push(end_node);
+            }
+        }
+    }
+
+    let position = |node: usize| graph.nodes[node].position;
+    let angle = |from: usize, to: usize| {
+        let (a, b) = (position(from), position(to));
+        f64::atan2(b.y() - a.y(), b.x() - a.x())
+    };
+
+    // outgoing half-edges of every node as (angle, target, edge), sorted
+    // counter-clockwise
+    let mut outgoing: Vec<Vec<(f64, usize, usize)>> = vec![Vec::new(); graph.nodes.len()];
+    for (index, (start, end, _)) in edges.iter().enumerate() {
+        if alive[index] {
+            outgoing[*start].push((angle(*start, *end), *end, index));
+            outgoing[*end].push((angle(*end, *start), *start, index));
+        }
+    }
+    for half_edges in outgoing.iter_mut() {
+        half_edges.sort_by(|a, b| a.0.total_cmp(&b.0));
+    }
+
+    let mut visited: HashSet<(usize, usize)> = HashSet::new();
+    let mut blocks = Vec::new();
+
+    for (index, (start, end, _)) in edges.iter().enumerate() {
+        if !alive[index] {
+            continue;
+        }
+
+        for (from, to) in [(*start, *end), (*end, *start)] {
+            if visited.contains(&(from, to)) {
+                continue;
+            }
+
+            if let Some(block) = trace_face(from, to, &outgoing, &edges, &mut visited, &position) {
+                blocks.push(block);
+            }
+        }
+    }
+
+    blocks
+}
+
+/// Follows the face to the left of the half-edge `from -> to` back to its
+/// start, marking every half-edge on the way as visited.
+fn trace_face(
+    from: usize,
+    to: usize,
+    outgoing: &[Vec<(f64, usize, usize)>],
+    edges: &[(usize, usize, usize)],
+    visited: &mut HashSet<(usize, usize)>,
+    position: &dyn Fn(usize) -> Point<f64>,
+) -> Option<Block> {
+    let mut path: Vec<HalfEdge> = Vec::new();
+    let (mut current_from, mut current_to) = (from, to);
+
+    loop {
+        visited.insert((current_from, current_to));
+
+        let half_edges = &outgoing[current_to];
+        let back = half_edges
+            .iter()
+            .position(|(_, target, _)| *target == current_from)?;
+        let (_, _, edge) = half_edges[back];
+        path.push((current_from, current_to, edges[edge].2));
+
+        // the next road clockwise from the one we arrived on
+        let (_, next, _) = half_edges[(back + half_edges.len() - 1) % half_edges.len()];
+
+        current_from = current_to;
+        current_to = next;
+
+        if (current_from, current_to) == (from, to) {
+            break;
+        }
+        if visited.contains(&(current_from, current_to)) {
+            return None;
+        }
+    }
+
+    // the outline comes out counter-clockwise and loops hanging inside the
+    // block clockwise
+    let mut loops: Vec<(f64, Vec<usize>, Vec<HalfEdge>)> = split_at_bridges(path)
+        .into_iter()
+        .map(|ring| {
+            let nodes: Vec<usize> = ring.iter().map(|(from, _, _)| *from).collect();
+            (signed_area(&nodes, position), nodes, ring)
+        })
+        // slivers left by roads folding back on themselves
+        .filter(|(area, nodes, _)| nodes.len() >= 3 && area.abs() > EPSILON.sqrt())
+        .collect();
+    let outermost =
+        (0..loops.len()).max_by(|a, b| loops[*a].0.abs().total_cmp(&loops[*b].0.abs()))?;
+    let (outer_area, nodes, ring) = loops.swap_remove(outermost);
+
+    // the unbounded outer face runs clockwise
+    if outer_area < 0.0 {
+        return None;
+    }
+    let holes = loops.into_iter().filter(|(area, _, _)| *area < 0.0);
+
+    let to_line = |nodes: &[usize]| {
+        let coords: Vec<Coord<f64>> = nodes.iter().map(|node| position(*node).into()).collect();
+        LineString::from(coords)
+    };
+    let perimeter_of = |nodes: &[usize]| {
+        (0..nodes.len())
+            .map(|i| length(position(nodes[i]), position(nodes[(i + 1) % nodes.len()])))
+            .sum::<f64>()
+    };
+
+    let mut area = outer_area;
+    let mut perimeter = perimeter_of(&nodes);
+    let mut segments: Vec<usize> = ring.iter().map(|(_, _, segment)| *segment).collect();
+    let mut interiors = Vec::new();
+    for (hole_area, hole_nodes, hole_ring) in holes {
+        area += hole_area;
+        perimeter += perimeter_of(&hole_nodes);
+        segments.extend(hole_ring.iter().map(|(_, _, segment)| *segment));
+        interiors.push(to_line(&hole_nodes));
+    }
+
+    Some(Block {
+        polygon: Polygon::new(to_line(&nodes), interiors),
+        area,
+        perimeter,
+        segments,
+        zone: None,
+    })
+}
+
+/// Splits a face into its closed loops by dropping every road the face runs
+/// along in both directions, such as the only road into a loop inside a
+/// block.
+fn split_at_bridges(path: Vec<HalfEdge>) -> Vec<Vec<HalfEdge>> {
+    for (i, (from, to, _)) in path.iter().enumerate() {
+        let Some(j) = (i + 1..path.len()).find(|j| path[*j].0 == *to && path[*j].1 == *from) else {
+            continue;
+        };
+
+        let inner = path[i + 1..j].to_vec();
+        let outer = path[..i]
+            .iter()
+            .chain(path[j + 1..].iter())
+            .copied()
+            .collect();
+
+        let mut loops = split_at_bridges(outer);
+        loops.extend(split_at_bridges(inner));
+        return loops;
+    }
+
+    if path.is_empty() {
+        Vec::new()
+    } else {
+        vec![path]
+    }
+}
+
+/// Area enclosed by the nodes, positive when they run counter-clockwise.
+fn signed_area(nodes: &[usize], position: &dyn Fn(usize) -> Point<f64>) -> f64 {
+    let mut area = 0.0;
+    for (i, node) in nodes.iter().enumerate() {
+        let (a, b) = (position(*node), position(nodes[(i + 1) % nodes.len()]));
+        area += a.x() * b.y() - b.x() * a.y();
+    }
+
+    area / 2.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{config::GenerationConfig, segment::CollisionMetaInfo};
+
+    fn segment(id: usize, start: (f64, f64), end: (f64, f64)) -> Segment {
+        Segment::new(
+            Point::from(start),
+            Point::from(end),
+            &0.0,
+            &CollisionMetaInfo::default(),
+            &id,
+            &GenerationConfig::default(),
+        )
+    }
+
+    /// A square of side `size` with its south-west corner at `corner`, as
+    /// four segments numbered from `first_id` counter-clockwise.
+    fn square(first_id: usize, corner: (f64, f64), size: f64) -> Vec<Segment> {
+        let (x, y) = corner;
+        let corners = [(x, y), (x + size, y), (x + size, y + size), (x, y + size)];
+        (0..4)
+            .map(|i| segment(first_id + i, corners[i], corners[(i + 1) % 4]))
+            .collect()
+    }
+
+    fn sorted(ids: &[usize]) -> Vec<usize> {
+        let mut ids = ids.to_vec();
+        ids.sort();
+        ids
+    }
+
+    #[test]
+    fn grid_of_four_cells_gives_four_blocks() {
+        // rows of east-west roads get ids 0 to 5, columns of north-south
+        // roads 6 to 11
+        let mut segments = Vec::new();
+        for row in 0..3 {
+            for col in 0..2 {
+                let (x, y) = (col as f64 * 100.0, row as f64 * 100.0);
+                segments.push(segment(row * 2 + col, (x, y), (x + 100.0, y)));
+            }
+        }
+        for col in 0..3 {
+            for row in 0..2 {
+                let (x, y) = (col as f64 * 100.0, row as f64 * 100.0);
+                segments.push(segment(6 + col * 2 + row, (x, y), (x, y + 100.0)));
+            }
+        }
+
+        let blocks = extract_blocks(&segments);
+        assert_eq!(blocks.len(), 4);
+
+        let mut found: Vec<Vec<usize>> = blocks
+            .iter()
+            .map(|block| {
+                assert!((block.area - 10000.0).abs() < 1e-6);
+                assert!((block.perimeter - 400.0).abs() < 1e-6);
+                assert!(block.polygon.interiors().is_empty());
+                sorted(&block.segments)
+            })
+            .collect();
+        found.sort();
+
+        // south, north, west and east side of every cell
+        let mut expected: Vec<Vec<usize>> = [(0, 0), (1, 0), (0, 1), (1, 1)]
+            .iter()
+            .map(|(col, row)| {
+                sorted(&[
+                    row * 2 + col,
+                    (row + 1) * 2 + col,
+                    6 + col * 2 + row,
+                    6 + (col + 1) * 2 + row,
+                ])
+            })
+            .collect();
+        expected.sort();
+
+        assert_eq!(found, expected);
+    }
+
+    #[test]
+    fn dead_ends_are_left_out() {
+        let mut segments = square(0, (0.0, 0.0), 100.0);
+        // one road leading away from the block and one into it
+        segments.push(segment(4, (0.0, 0.0), (-50.0, -50.0)));
+        segments.push(segment(5, (100.0, 100.0), (70.0, 70.0)));
+        segments.push(segment(6, (70.0, 70.0), (70.0, 40.0)));
+
+        let blocks = extract_blocks(&segments);
+        assert_eq!(blocks.len(), 1);
+        assert!((blocks[0].area - 10000.0).abs() < 1e-6);
+        assert!((blocks[0].perimeter - 400.0).abs() < 1e-6);
+        assert_eq!(sorted(&blocks[0].segments), vec![0, 1, 2, 3]);
+    }
+
+    #[test]
+    fn loop_hanging_by_one_road_is_a_hole() {
+        let mut segments = square(0, (0.0, 0.0), 300.0);
+        segments.extend(square(4, (100.0, 100.0), 100.0));
+        segments.push(segment(8, (0.0, 0.0), (100.0, 100.0)));
+
+        let mut blocks = extract_blocks(&segments);
+        assert_eq!(blocks.len(), 2);
+        blocks.sort_by(|a, b| a.area.total_cmp(&b.area));
+
+        let (inner, outer) = (&blocks[0], &blocks[1]);
+        assert!((inner.area - 10000.0).abs() < 1e-6);
+        assert_eq!(sorted(&inner.segments), vec![4, 5, 6, 7]);
+
+        // the road into the loop belongs to neither block
+        assert_eq!(outer.polygon.interiors().len(), 1);
+        assert!((outer.area - 80000.0).abs() < 1e-6);
+        assert!((outer.perimeter - 1600.0).abs() < 1e-6);
+        assert_eq!(outer.segments.len(), 8);
+        assert_eq!(sorted(&outer.segments), (0..8).collect::<Vec<_>>());
+    }
+}
@@ -0,0 +1,155 @@
+use geo::{Coord, LineString, Point, Polygon};
+use rand::{rngs::StdRng, Rng};
+
+use super::{
+    collision::{CollisionLimits, CollisionObject, CollisionProperties, CollisionType},
+    config::GenerationConfig,
+    export::segments_extent,
+    math::*,
+    quad_tree::Quadtree,
+    segment::Segment,
+};
+
+/// A rectangular building footprint facing a road.
+#[derive(Debug, Clone)]
+pub struct Building {
+    pub id: usize,
+    /// Id of the segment the building fronts.
+    pub segment: usize,
+    /// Heading of the fronted road in degrees, with 0 pointing north.
+    pub dir: f64,
+    pub collider: CollisionObject,
+    pub polygon: Polygon<f64>,
+}
+
+/// Lines both sides of every road with building lots.
+///
+/// Lots are laid out back to back along each segment, `building_setback`
+/// away from the road edge. Each one is built on with a chance of
+/// `building_density` times `density_at` its centre, and only if it stays
+/// clear of every road and of the buildings placed before it. Bridges get
+/// no buildings.
+pub fn place_buildings(
+    segments: &[Segment],
+    config: &GenerationConfig,
+    density_at: &dyn Fn(Point<f64>) -> f64,
+    rng: &mut StdRng,
+) -> Vec<Building> {
+    let (width, depth, setback) = (
+        config.building_width,
+        config.building_depth,
+        config.building_setback,
+    );
+
+    // wide enough for the buildings behind the outermost roads
+    let max_road_width = segments.iter().map(Segment::width).fold(0.0, f64::max);
+    let Ok(extent) = segments_extent(segments, depth + setback + max_road_width) else {
+        return Vec::new();
+    };
+
+    // roads and buildings share one index, keyed by their position here
+    let mut colliders: Vec<CollisionObject> = Vec::new();
+    let mut quad_tree = Quadtree::with_bounds(CollisionLimits {
+        x: extent.min_x,
+        y: extent.min_y,
+        width: extent.width(),
+        height: extent.height(),
+        id: None,
+    });
+    for (index, segment) in segments.iter().enumerate() {
+        let mut collider = segment.collider.clone();
+        quad_tree.insert(collider.limits(), index);
+        colliders.push(collider);
+    }
+
+    let mut buildings = Vec::new();
+
+    for segment in segments.iter() {
+        if segment.q.bridge {
+            continue;
+        }
+
+        let road_length = length(segment.r.start, segment.r.end);
+        let lots = ((road_length - setback) / (width + setback)).floor();
+        if lots < 1.0 {
+            continue;
+        }
+
+        let along = mult_v_scalar(
+            subtract_points(segment.r.end, segment.r.start),
+            1.0 / road_length,
+        );
+        let across = Point::new(along.y(), -along.x());
+        let dir = calculate_direction(&segment.r);
+
+        // centre the row of lots on the segment
+        let first = (road_length - lots * (width + setback) + setback + width) / 2.0;
+        let offset = segment.width() / 2.0 + setback + depth / 2.0;
+
+        for side in [-1.0, 1.0] {
+            for lot in 0..lots as usize {
+                let center = add_points(
+                    add_points(
+                        segment.r.start,
+                        mult_v_scalar(along, first + lot as f64 * (width + setback)),
+                    ),
+                    mult_v_scalar(across, side * offset),
+                );
+
+                let chance = config.building_density * density_at(center);
+                if rng.gen::<f64>() >= chance {
+                    continue;
+                }
+
+                let corners = footprint(center, along, across, width, depth);
+                let mut collider = CollisionObject::new(
+                    CollisionType::Rect,
+                    CollisionProperties {
+                        corners: corners.to_vec(),
+                        start: center,
+                        end: center,
+                        center,
+                        radius: 0.0,
+                        width,
+                    },
+                    colliders.len(),
+                );
+
+                let blocked = quad_tree
+                    .retrieve(&collider.limits())
+                    .any(|index| collider.collide(&mut colliders[*index]));
+                if blocked {
+                    continue;
+                }
+
+                let ring: Vec<Coord<f64>> = corners.iter().map(|corner| (*corner).into()).collect();
+                buildings.push(Building {
+                    id: buildings.len(),
+                    segment: segment.id,
+                    dir,
+                    collider: collider.clone(),
+                    polygon: Polygon::new(LineString::from(ring), Vec::new()),
+                });
+
+                quad_tree.insert(collider.limits(), colliders.len());
+                colliders.push(collider);
+            }
+        }
+    }
+
+    buildings
+}
+
+/// Corners of a `width` by `depth` rectangle around `center`, in order
+/// around the outline.
+fn footprint(center: Point, along: Point, across: Point, width: f64, depth: f64) -> [Point; 4] {
+    let half_along = mult_v_scalar(along, width / 2.0);
+    let half_across = mult_v_scalar(across, depth / 2.0);
+
+    [
+        subtract_points(subtract_points(center, half_along), half_across),
+        subtract_points(add_points(center, half_along), half_across),
+        add_points(add_points(center, half_along), half_across),
+        add_points(subtract_points(center, half_along), half_across),
+    ]
+}
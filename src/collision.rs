@@ -2,13 +2,11 @@ use geo::Point;
 
 use super::{
     math::*,
-    segment::Road,
     utility::{extended_max, extended_min},
 };
 
 #[derive(Debug, Clone)]
 pub struct CollisionObject {
-    road: Road,
     collision_type: CollisionType,
     collision_properties: CollisionProperties,
     collision_revision: u32,
@@ -43,15 +41,23 @@ pub struct CollisionLimits {
     pub id: Option<usize>,
 }
 
+impl CollisionLimits {
+    /// Whether the two boxes overlap or touch.
+    pub fn overlaps(&self, other: &CollisionLimits) -> bool {
+        !(self.x + self.width < other.x
+            || other.x + other.width < self.x
+            || self.y + self.height < other.y
+            || other.y + other.height < self.y)
+    }
+}
+
 impl CollisionObject {
     pub fn new(
-        road: Road,
         collision_type: CollisionType,
         collision_properties: CollisionProperties,
         id: usize,
     ) -> Self {
         CollisionObject {
-            road,
             collision_type,
             collision_properties,
             collision_revision: 1,
@@ -78,6 +84,11 @@ impl CollisionObject {
         }
     }
 
+    /// Width of a line collider, i.e. of the road it belongs to.
+    pub fn width(&self) -> f64 {
+        self.collision_properties.width
+    }
+
     pub fn limits(&mut self) -> CollisionLimits {
         if self.collision_revision != self.limits_revision.unwrap_or_default() {
             self.limits_revision = Some(self.collision_revision);
@@ -116,23 +127,28 @@ impl CollisionObject {
                         id: self.id,
                     })
                 }
-                CollisionType::Line => Some(CollisionLimits {
-                    x: f64::min(
-                        self.collision_properties.start.x(),
-                        self.collision_properties.end.x(),
-                    ),
-                    y: f64::min(
-                        self.collision_properties.start.y(),
-                        self.collision_properties.end.y(),
-                    ),
-                    width: f64::abs(
-                        self.collision_properties.start.x() - self.collision_properties.end.x(),
-                    ),
-                    height: f64::abs(
-                        self.collision_properties.start.y() - self.collision_properties.end.y(),
-                    ),
-                    id: self.id,
-                }),
+                CollisionType::Line => {
+                    // the road surface reaches half its width past the centre line
+                    let half_width = self.collision_properties.width / 2.0;
+
+                    Some(CollisionLimits {
+                        x: f64::min(
+                            self.collision_properties.start.x(),
+                            self.collision_properties.end.x(),
+                        ) - half_width,
+                        y: f64::min(
+                            self.collision_properties.start.y(),
+                            self.collision_properties.end.y(),
+                        ) - half_width,
+                        width: f64::abs(
+                            self.collision_properties.start.x() - self.collision_properties.end.x(),
+                        ) + 2.0 * half_width,
+                        height: f64::abs(
+                            self.collision_properties.start.y() - self.collision_properties.end.y(),
+                        ) + 2.0 * half_width,
+                        id: self.id,
+                    })
+                }
                 CollisionType::Circle => Some(CollisionLimits {
                     x: self.collision_properties.center.x() - self.collision_properties.radius,
                     y: self.collision_properties.center.y() - self.collision_properties.radius,
@@ -146,16 +162,12 @@ impl CollisionObject {
         self.cached_limits.unwrap()
     }
 
-    fn collide(&mut self, other: &mut CollisionObject) -> bool {
+    pub fn collide(&mut self, other: &mut CollisionObject) -> bool {
         // Implementation of collide function
         let obj_limits = self.limits();
         let other_limits = other.limits();
 
-        if obj_limits.x + obj_limits.width < other_limits.x
-            || other_limits.x + other_limits.width < obj_limits.x
-            || obj_limits.y + obj_limits.height < other_limits.y
-            || other_limits.y + other_limits.height < obj_limits.y
-        {
+        if !obj_limits.overlaps(&other_limits) {
             return false;
         }
 
@@ -204,9 +216,8 @@ impl CollisionObject {
         let corners: &Vec<Point> = &rect_props.corners;
 
         // Check for corner intersections with circle
-        for i in 0..corners.len() {
-            if length2(corners[i], circle_props.center) <= circle_props.radius * circle_props.radius
-            {
+        for corner in corners.iter() {
+            if length2(*corner, circle_props.center) <= circle_props.radius * circle_props.radius {
                 return true;
             }
         }
@@ -259,11 +270,11 @@ impl CollisionObject {
                 subtract_points(line_props.end, half_width_perp_dir),
                 add_points(line_props.end, half_width_perp_dir),
             ],
-            start: Point::new(0.0, 0.0), // Placeholder, update accordingly
-            end: Point::new(0.0, 0.0),   // Placeholder, update accordingly
-            center: Point::new(0.0, 0.0), // Placeholder, update accordingly
+            start: line_props.start,
+            end: line_props.end,
+            center: mult_v_scalar(add_points(line_props.start, line_props.end), 0.5),
             radius: 0.0,
-            width: 0.0, // Placeholder, update accordingly
+            width: line_props.width,
         }
     }
 
@@ -293,11 +304,11 @@ impl CollisionObject {
             let mut projected_vectors_b = Vec::new();
 
             for corner in c_a.iter() {
-                projected_vectors_a.push(project(corner.clone(), *axis).projected);
+                projected_vectors_a.push(project(*corner, *axis).projected);
             }
 
             for corner in c_b.iter() {
-                projected_vectors_b.push(project(corner.clone(), *axis).projected);
+                projected_vectors_b.push(project(*corner, *axis).projected);
             }
 
             // Calculate relative positions of rectangles on axis
@@ -1,39 +1,340 @@
-use crate::math::random_range;
+use std::{fs, path::Path};
+
+use rand::{rngs::StdRng, Rng};
+use serde::{Deserialize, Deserializer};
+
+use crate::{
+    error::{CitygenError, Result},
+    math::random_range,
+    pattern::GrowthPattern,
+    road_class::{deserialize_class, RoadClass, RoadClassConfig},
+    utility::parse_error,
+    zoning::ZoningConfig,
+};
 
-pub const HIGHWAY_SEGMENT_WIDTH: f64 = 16.0;
-pub const HIGHWAY_BRANCH_POPULATION_THRESHOLD: f64 = 0.1;
-pub const HIGHWAY_BRANCH_PROBABILITY: f64 = 0.05;
-pub const DEFAULT_SEGMENT_WIDTH: f64 = 6.0;
-pub const DEFAULT_SEGMENT_LENGTH: f64 = 300.0;
 pub const EPSILON: f64 = 0.00000001;
-pub const MINIMUM_INTERSECTION_DEVIATION: f64 = 30.0;
-pub const ROAD_SNAP_DISTANCE: f64 = 50.0;
-pub const NORMAL_BRANCH_TIME_DELAY_FROM_HIGHWAY: f64 = 5.0;
-pub const NORMAL_BRANCH_POPULATION_THRESHOLD: f64 = 0.1;
-pub const DEFAULT_BRANCH_PROBABILITY: f64 = 0.4;
-pub const HIGHWAY_SEGMENT_LENGTH: f64 = 400.0;
-pub const SEGMENT_COUNT_LIMIT: f64 = 200.0;
 
-const BRANCH_ANGLE_DEV: f64 = 3.0;
-const FORWARD_ANGLE_DEV: f64 = 15.0;
+/// Tuning parameters read by the generator at runtime.
+///
+/// Every field has a default matching the original CityGen values, so a
+/// partial config only needs to list the knobs it changes.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct GenerationConfig {
+    #[serde(deserialize_with = "motorway")]
+    pub motorway: RoadClassConfig,
+    #[serde(deserialize_with = "arterial")]
+    pub arterial: RoadClassConfig,
+    #[serde(deserialize_with = "collector")]
+    pub collector: RoadClassConfig,
+    #[serde(deserialize_with = "local")]
+    pub local: RoadClassConfig,
+    #[serde(deserialize_with = "alley")]
+    pub alley: RoadClassConfig,
+    pub minimum_intersection_deviation: f64,
+    pub road_snap_distance: f64,
+    pub segment_count_limit: usize,
+    pub branch_angle_dev: f64,
+    pub forward_angle_dev: f64,
+    /// Longest stretch of water a major road may bridge. `0.0` disables bridges.
+    pub max_bridge_length: f64,
+    /// Steepest rise over run a road may climb when an elevation map is set.
+    pub max_slope: f64,
+    /// Angle in degrees by which a too steep road is turned per attempt.
+    pub slope_angle_step: f64,
+    /// Layout of normal roads wherever `GlobalConfig::pattern` is not replaced.
+    pub growth_pattern: GrowthPattern,
+    /// Rotation of the grid pattern in degrees, clockwise from north.
+    pub grid_orientation: f64,
+    /// Distance between parallel streets of the grid pattern.
+    pub grid_block_size: f64,
+    /// Centres the radial pattern's rings and spokes are laid around.
+    pub radial_centres: Vec<[f64; 2]>,
+    /// How far normal roads are pulled from their organic course onto the
+    /// grid or radial pattern, from `0.0` to `1.0`.
+    pub pattern_blend: f64,
+    /// Frontage of a building footprint along its road.
+    pub building_width: f64,
+    /// Extent of a building footprint away from its road.
+    pub building_depth: f64,
+    /// Gap kept between a footprint and the edge of its road, and between
+    /// neighbouring footprints.
+    pub building_setback: f64,
+    /// Chance of a lot being built on per unit of population, so that
+    /// a density of `2.0` fills every lot where the population is `0.5`.
+    pub building_density: f64,
+    /// Blocks are cut into parcels until none is larger than this.
+    pub parcel_max_area: f64,
+    /// Parcels smaller than this are left over from cuts and dropped.
+    pub parcel_min_area: f64,
+    /// Rule table giving blocks and parcels their land use.
+    pub zoning: ZoningConfig,
+    /// Roads growth starts from. When empty, a pair of motorways heads east
+    /// and west from the origin.
+    pub seeds: Vec<SeedSegment>,
+    /// Run `validate_links` after every accepted segment and fail on the
+    /// first inconsistency. Slow, intended for tests and debugging.
+    pub check_links_each_step: bool,
+}
+
+/// A starting segment, given by where it begins and which way it heads.
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SeedSegment {
+    pub position: [f64; 2],
+    /// Heading in degrees, with 0 pointing north and 90 east.
+    pub direction: f64,
+    #[serde(default = "local_class")]
+    pub class: RoadClass,
+}
+
+fn local_class() -> RoadClass {
+    RoadClass::Local
+}
+
+impl Default for GenerationConfig {
+    fn default() -> Self {
+        GenerationConfig {
+            motorway: RoadClassConfig::default_for(RoadClass::Motorway),
+            arterial: RoadClassConfig::default_for(RoadClass::Arterial),
+            collector: RoadClassConfig::default_for(RoadClass::Collector),
+            local: RoadClassConfig::default_for(RoadClass::Local),
+            alley: RoadClassConfig::default_for(RoadClass::Alley),
+            minimum_intersection_deviation: 30.0,
+            road_snap_distance: 50.0,
+            segment_count_limit: 200,
+            branch_angle_dev: 3.0,
+            forward_angle_dev: 15.0,
+            max_bridge_length: 200.0,
+            max_slope: 0.12,
+            slope_angle_step: 10.0,
+            growth_pattern: GrowthPattern::Organic,
+            grid_orientation: 0.0,
+            grid_block_size: 300.0,
+            radial_centres: vec![[0.0, 0.0]],
+            pattern_blend: 1.0,
+            building_width: 15.0,
+            building_depth: 15.0,
+            building_setback: 3.0,
+            building_density: 2.0,
+            parcel_max_area: 2000.0,
+            parcel_min_area: 200.0,
+            zoning: ZoningConfig::default(),
+            seeds: Vec::new(),
+            check_links_each_step: false,
+        }
+    }
+}
+
+impl GenerationConfig {
+    pub fn road_class(&self, class: RoadClass) -> &RoadClassConfig {
+        match class {
+            RoadClass::Motorway => &self.motorway,
+            RoadClass::Arterial => &self.arterial,
+            RoadClass::Collector => &self.collector,
+            RoadClass::Local => &self.local,
+            RoadClass::Alley => &self.alley,
+        }
+    }
+
+    /// Reads a config from a `.toml` or `.json` file and validates it.
+    pub fn from_file(path: &Path) -> Result<Self> {
+        let contents = fs::read_to_string(path)?;
+        let config: GenerationConfig = match path.extension().and_then(|ext| ext.to_str()) {
+            Some("json") => {
+                serde_json::from_str(&contents).map_err(|e| parse_error(path, e.to_string()))?
+            }
+            Some("toml") => {
+                toml::from_str(&contents).map_err(|e| parse_error(path, e.to_string()))?
+            }
+            _ => {
+                return Err(CitygenError::UnsupportedFormat {
+                    path: path.to_path_buf(),
+                    expected: ".toml or .json",
+                })
+            }
+        };
+
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Checks that every value is in the range the generator can work with.
+    pub fn validate(&self) -> Result<()> {
+        let mut probabilities = vec![("pattern_blend".to_string(), self.pattern_blend)];
+        let mut positive = vec![
+            ("branch_angle_dev".to_string(), self.branch_angle_dev),
+            ("forward_angle_dev".to_string(), self.forward_angle_dev),
+            ("max_slope".to_string(), self.max_slope),
+            ("grid_block_size".to_string(), self.grid_block_size),
+            ("building_width".to_string(), self.building_width),
+            ("building_depth".to_string(), self.building_depth),
+            ("parcel_max_area".to_string(), self.parcel_max_area),
+        ];
+        let mut non_negative = vec![
+            ("road_snap_distance".to_string(), self.road_snap_distance),
+            ("max_bridge_length".to_string(), self.max_bridge_length),
+            ("building_setback".to_string(), self.building_setback),
+            ("building_density".to_string(), self.building_density),
+            ("parcel_min_area".to_string(), self.parcel_min_area),
+        ];
+
+        for class in RoadClass::ALL {
+            let road = self.road_class(class);
+            let field = |name: &str| format!("{}.{}", class.as_str(), name);
+
+            probabilities.push((field("branch_probability"), road.branch_probability));
+            probabilities.push((
+                field("branch_population_threshold"),
+                road.branch_population_threshold,
+            ));
+            positive.push((field("segment_length"), road.segment_length));
+            positive.push((field("width"), road.width));
+            non_negative.push((field("time_delay"), road.time_delay));
+        }
+
+        for (name, value) in probabilities {
+            if !(0.0..=1.0).contains(&value) {
+                return Err(invalid(
+                    &name,
+                    format!("must be between 0 and 1, got {}", value),
+                ));
+            }
+        }
+
+        for (name, value) in positive {
+            if !value.is_finite() || value <= 0.0 {
+                return Err(invalid(
+                    &name,
+                    format!("must be greater than 0, got {}", value),
+                ));
+            }
+        }
+
+        for (name, value) in non_negative {
+            if !value.is_finite() || value < 0.0 {
+                return Err(invalid(
+                    &name,
+                    format!("must not be negative, got {}", value),
+                ));
+            }
+        }
+
+        if !(0.0..=90.0).contains(&self.minimum_intersection_deviation) {
+            return Err(invalid(
+                "minimum_intersection_deviation",
+                format!(
+                    "must be between 0 and 90 degrees, got {}",
+                    self.minimum_intersection_deviation
+                ),
+            ));
+        }
+
+        if !self.grid_orientation.is_finite() {
+            return Err(invalid(
+                "grid_orientation",
+                format!("must be a finite angle, got {}", self.grid_orientation),
+            ));
+        }
+
+        if self.growth_pattern == GrowthPattern::Radial && self.radial_centres.is_empty() {
+            return Err(invalid(
+                "radial_centres",
+                "must list at least one centre for the radial pattern".into(),
+            ));
+        }
+
+        if self.parcel_min_area >= self.parcel_max_area {
+            return Err(invalid(
+                "parcel_min_area",
+                format!(
+                    "must be less than parcel_max_area ({}), got {}",
+                    self.parcel_max_area, self.parcel_min_area
+                ),
+            ));
+        }
+
+        for (index, rule) in self.zoning.rules.iter().enumerate() {
+            for (name, min, max) in rule.bounds() {
+                if let (Some(min), Some(max)) = (min, max) {
+                    if min > max {
+                        return Err(invalid(
+                            &format!("zoning.rules[{}].min_{}", index, name),
+                            format!("must not exceed max_{} ({}), got {}", name, max, min),
+                        ));
+                    }
+                }
+            }
+        }
+
+        if !(self.slope_angle_step > 0.0 && self.slope_angle_step <= 90.0) {
+            return Err(invalid(
+                "slope_angle_step",
+                format!(
+                    "must be greater than 0 and at most 90 degrees, got {}",
+                    self.slope_angle_step
+                ),
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+fn motorway<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> std::result::Result<RoadClassConfig, D::Error> {
+    deserialize_class(deserializer, RoadClass::Motorway)
+}
+
+fn arterial<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> std::result::Result<RoadClassConfig, D::Error> {
+    deserialize_class(deserializer, RoadClass::Arterial)
+}
+
+fn collector<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> std::result::Result<RoadClassConfig, D::Error> {
+    deserialize_class(deserializer, RoadClass::Collector)
+}
+
+fn local<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> std::result::Result<RoadClassConfig, D::Error> {
+    deserialize_class(deserializer, RoadClass::Local)
+}
+
+fn alley<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> std::result::Result<RoadClassConfig, D::Error> {
+    deserialize_class(deserializer, RoadClass::Alley)
+}
+
+fn invalid(field: &str, message: String) -> CitygenError {
+    CitygenError::InvalidConfig {
+        field: field.to_string(),
+        message,
+    }
+}
 
 pub enum AngleDirection {
     Branch,
     Forward,
 }
 
-pub fn random_angle(direction: AngleDirection) -> f64 {
+pub fn random_angle(direction: AngleDirection, config: &GenerationConfig, rng: &mut StdRng) -> f64 {
     let limit = match direction {
-        AngleDirection::Branch => BRANCH_ANGLE_DEV,
-        AngleDirection::Forward => FORWARD_ANGLE_DEV,
+        AngleDirection::Branch => config.branch_angle_dev,
+        AngleDirection::Forward => config.forward_angle_dev,
     };
 
     // non-linear distribution
     let non_uniform_norm = f64::powf(limit.abs(), 3.0);
     let mut val: f64 = 0.0;
 
-    while val == 0.0 || rand::random::<f64>() < f64::powf(val.abs(), 3.0) / non_uniform_norm {
-        val = random_range(-limit, limit);
+    while val == 0.0 || rng.gen::<f64>() < f64::powf(val.abs(), 3.0) / non_uniform_norm {
+        val = random_range(rng, -limit, limit);
     }
 
     val
@@ -0,0 +1,87 @@
+use std::{fmt, io, path::PathBuf};
+
+use crate::validate::LinkViolation;
+
+/// Everything that can go wrong while loading a config, growing a network or
+/// exporting it.
+#[derive(Debug)]
+pub enum CitygenError {
+    Io(io::Error),
+    /// An input file that could not be parsed.
+    Parse {
+        path: PathBuf,
+        message: String,
+    },
+    /// A file whose extension is not one of the `expected` formats.
+    UnsupportedFormat {
+        path: PathBuf,
+        expected: &'static str,
+    },
+    /// An image that could not be used as a raster.
+    InvalidRaster {
+        path: PathBuf,
+        message: String,
+    },
+    /// A config value outside the range the generator can work with.
+    InvalidConfig {
+        field: String,
+        message: String,
+    },
+    /// An export that needs at least one segment was given none.
+    EmptyNetwork,
+    /// A link refers to a segment id that does not exist.
+    MissingSegment(usize),
+    /// The link graph failed validation.
+    InconsistentLinks(Vec<LinkViolation>),
+}
+
+pub type Result<T> = std::result::Result<T, CitygenError>;
+
+impl fmt::Display for CitygenError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CitygenError::Io(e) => write!(f, "{}", e),
+            CitygenError::Parse { path, message } => {
+                write!(f, "could not parse {}: {}", path.display(), message)
+            }
+            CitygenError::UnsupportedFormat { path, expected } => write!(
+                f,
+                "unsupported file format {}, expected {}",
+                path.display(),
+                expected
+            ),
+            CitygenError::InvalidRaster { path, message } => {
+                write!(f, "invalid raster {}: {}", path.display(), message)
+            }
+            CitygenError::InvalidConfig { field, message } => {
+                write!(f, "`{}` {}", field, message)
+            }
+            CitygenError::EmptyNetwork => write!(f, "the network has no segments"),
+            CitygenError::MissingSegment(id) => {
+                write!(f, "link graph refers to missing segment {}", id)
+            }
+            CitygenError::InconsistentLinks(violations) => {
+                write!(f, "{} link violation(s)", violations.len())?;
+                for violation in violations.iter() {
+                    write!(f, "\n  {}", violation)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl std::error::Error for CitygenError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            CitygenError::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for CitygenError {
+    fn from(e: io::Error) -> Self {
+        CitygenError::Io(e)
+    }
+}
@@ -0,0 +1,390 @@
+use std::{fs::File, io::Write, path::Path};
+
+use geojson::{feature::Id, Feature, FeatureCollection, GeoJson, Geometry, Value};
+use serde_json::Map;
+
+use super::{
+    block::Block,
+    building::Building,
+    error::{CitygenError, Result},
+    math::calculate_direction,
+    parcel::Parcel,
+    population::PopulationMap,
+    raster::{Extent, Raster},
+    road_class::RoadClass,
+    segment::Segment,
+};
+
+/// Converts segments into a feature collection of line strings carrying the
+/// `dir`, `severed`, `class`, `width`, `highway` and `bridge` properties, plus
+/// the start and end elevation when terrain was used.
+pub fn segments_to_geojson(segments: &[Segment]) -> GeoJson {
+    let mut features = Vec::new();
+
+    for segment in segments.iter() {
+        let coordinates = vec![
+            vec![segment.r.start.x(), segment.r.start.y()],
+            vec![segment.r.end.x(), segment.r.end.y()],
+        ];
+        let line_string_geometry = Geometry::new(Value::LineString(coordinates));
+
+        let mut props = Map::new();
+        let dir_val = serde_json::Number::from_f64(calculate_direction(&segment.r));
+        props.insert("dir".to_string(), dir_val.into());
+
+        let severed_val = serde_json::Value::Bool(segment.q.severed);
+        props.insert("severed".to_string(), severed_val);
+
+        let class_val = serde_json::Value::from(segment.q.class.as_str());
+        props.insert("class".to_string(), class_val);
+
+        let width_val = serde_json::Number::from_f64(segment.width());
+        props.insert("width".to_string(), width_val.into());
+
+        let highway_val = serde_json::Value::Bool(segment.q.class == RoadClass::Motorway);
+        props.insert("highway".to_string(), highway_val);
+
+        let bridge_val = serde_json::Value::Bool(segment.q.bridge);
+        props.insert("bridge".to_string(), bridge_val);
+
+        if let Some([start_elevation, end_elevation]) = segment.elevation {
+            let start_val = serde_json::Number::from_f64(start_elevation);
+            props.insert("elevation_start".to_string(), start_val.into());
+            let end_val = serde_json::Number::from_f64(end_elevation);
+            props.insert("elevation_end".to_string(), end_val.into());
+        }
+
+        let feature = Feature {
+            bbox: None,
+            geometry: Some(line_string_geometry),
+            id: Some(Id::Number(segment.id.into())),
+            properties: Some(props),
+            foreign_members: None,
+        };
+
+        features.push(feature);
+    }
+
+    feature_collection(features)
+}
+
+/// Writes segments to `path` as a GeoJSON feature collection.
+pub fn write_geojson(segments: &[Segment], path: &Path) -> Result<()> {
+    write_features(segments_to_geojson(segments), path)
+}
+
+fn feature_collection(features: Vec<Feature>) -> GeoJson {
+    GeoJson::from(FeatureCollection {
+        bbox: None,
+        features,
+        foreign_members: None,
+    })
+}
+
+/// Writes a feature collection built by one of the `*_to_geojson` functions
+/// to `path`.
+fn write_features(geojson: GeoJson, path: &Path) -> Result<()> {
+    let mut file = File::create(path)?;
+    file.write_all(geojson.to_string().as_bytes())?;
+
+    Ok(())
+}
+
+/// Converts blocks into polygon features with `area`, `perimeter` and
+/// `segments` properties, plus `zone` once zoned.
+pub fn blocks_to_geojson(blocks: &[Block]) -> GeoJson {
+    let mut features = Vec::new();
+
+    for (index, block) in blocks.iter().enumerate() {
+        let mut props = Map::new();
+        let area_val = serde_json::Number::from_f64(block.area);
+        props.insert("area".to_string(), area_val.into());
+        let perimeter_val = serde_json::Number::from_f64(block.perimeter);
+        props.insert("perimeter".to_string(), perimeter_val.into());
+        props.insert("segments".to_string(), block.segments.clone().into());
+        if let Some(zone) = block.zone {
+            props.insert("zone".to_string(), zone.as_str().into());
+        }
+
+        features.push(Feature {
+            bbox: None,
+            geometry: Some(Geometry::new(Value::from(&block.polygon))),
+            id: Some(Id::Number(index.into())),
+            properties: Some(props),
+            foreign_members: None,
+        });
+    }
+
+    feature_collection(features)
+}
+
+/// Writes blocks to `path` as a GeoJSON feature collection.
+pub fn write_blocks(blocks: &[Block], path: &Path) -> Result<()> {
+    write_features(blocks_to_geojson(blocks), path)
+}
+
+/// Converts building footprints into polygon features with the id of the
+/// fronted `segment` and its `dir`.
+pub fn buildings_to_geojson(buildings: &[Building]) -> GeoJson {
+    let mut features = Vec::new();
+
+    for building in buildings.iter() {
+        let mut props = Map::new();
+        props.insert("segment".to_string(), building.segment.into());
+        let dir_val = serde_json::Number::from_f64(building.dir);
+        props.insert("dir".to_string(), dir_val.into());
+
+        features.push(Feature {
+            bbox: None,
+            geometry: Some(Geometry::new(Value::from(&building.polygon))),
+            id: Some(Id::Number(building.id.into())),
+            properties: Some(props),
+            foreign_members: None,
+        });
+    }
+
+    feature_collection(features)
+}
+
+/// Writes building footprints to `path` as a GeoJSON feature collection.
+pub fn write_buildings(buildings: &[Building], path: &Path) -> Result<()> {
+    write_features(buildings_to_geojson(buildings), path)
+}
+
+/// Converts parcels into polygon features with their `area`, the fronted
+/// `segment`, the index of their `block` and their `zone` once zoned.
+pub fn parcels_to_geojson(parcels: &[Parcel]) -> GeoJson {
+    let mut features = Vec::new();
+
+    for (index, parcel) in parcels.iter().enumerate() {
+        let mut props = Map::new();
+        let area_val = serde_json::Number::from_f64(parcel.area);
+        props.insert("area".to_string(), area_val.into());
+        props.insert("segment".to_string(), parcel.segment.into());
+        props.insert("block".to_string(), parcel.block.into());
+        if let Some(zone) = parcel.zone {
+            props.insert("zone".to_string(), zone.as_str().into());
+        }
+
+        features.push(Feature {
+            bbox: None,
+            geometry: Some(Geometry::new(Value::from(&parcel.polygon))),
+            id: Some(Id::Number(index.into())),
+            properties: Some(props),
+            foreign_members: None,
+        });
+    }
+
+    feature_collection(features)
+}
+
+/// Writes parcels to `path` as a GeoJSON feature collection.
+pub fn write_parcels(parcels: &[Parcel], path: &Path) -> Result<()> {
+    write_features(parcels_to_geojson(parcels), path)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PopulationFormat {
+    /// One square polygon per cell with a `population` property.
+    GeoJson,
+    /// Binary grayscale image, values clamped to `0.0..=1.0`.
+    Pgm,
+    /// ESRI ASCII grid, which GIS tools read with its georeferencing.
+    Ascii,
+}
+
+impl PopulationFormat {
+    /// Picks the format from a `.geojson`, `.json`, `.pgm` or `.asc` extension.
+    pub fn from_path(path: &Path) -> Result<Self> {
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("geojson") | Some("json") => Ok(PopulationFormat::GeoJson),
+            Some("pgm") => Ok(PopulationFormat::Pgm),
+            Some("asc") => Ok(PopulationFormat::Ascii),
+            _ => Err(CitygenError::UnsupportedFormat {
+                path: path.to_path_buf(),
+                expected: ".geojson, .json, .pgm or .asc",
+            }),
+        }
+    }
+}
+
+/// Largest population grid `sample_population` builds, 4096 by 4096 cells.
+pub const MAX_POPULATION_CELLS: usize = 4096 * 4096;
+
+/// Bounding box of all segment endpoints, grown by `margin` on every side.
+pub fn segments_extent(segments: &[Segment], margin: f64) -> Result<Extent> {
+    if segments.is_empty() {
+        return Err(CitygenError::EmptyNetwork);
+    }
+
+    let mut extent = Extent {
+        min_x: f64::INFINITY,
+        min_y: f64::INFINITY,
+        max_x: f64::NEG_INFINITY,
+        max_y: f64::NEG_INFINITY,
+    };
+
+    for segment in segments.iter() {
+        for point in [segment.r.start, segment.r.end] {
+            extent.min_x = extent.min_x.min(point.x());
+            extent.min_y = extent.min_y.min(point.y());
+            extent.max_x = extent.max_x.max(point.x());
+            extent.max_y = extent.max_y.max(point.y());
+        }
+    }
+
+    Ok(Extent {
+        min_x: extent.min_x - margin,
+        min_y: extent.min_y - margin,
+        max_x: extent.max_x + margin,
+        max_y: extent.max_y + margin,
+    })
+}
+
+/// Samples the population at the centre of every `cell_size` square covering
+/// `extent`. The grid starts at the north-west corner of the extent and may
+/// hold at most `MAX_POPULATION_CELLS` cells.
+pub fn sample_population(
+    population: &dyn PopulationMap,
+    extent: Extent,
+    cell_size: f64,
+) -> Result<Raster> {
+    if !cell_size.is_finite() || cell_size <= 0.0 {
+        return Err(CitygenError::InvalidConfig {
+            field: "cell_size".to_string(),
+            message: format!("must be greater than 0, got {}", cell_size),
+        });
+    }
+    if !extent.is_valid() {
+        return Err(CitygenError::InvalidConfig {
+            field: "extent".to_string(),
+            message: format!(
+                "must be finite with each minimum below its maximum, got {:?}",
+                extent
+            ),
+        });
+    }
+
+    let columns = (extent.width() / cell_size).ceil().max(1.0);
+    let rows = (extent.height() / cell_size).ceil().max(1.0);
+    if columns * rows > MAX_POPULATION_CELLS as f64 {
+        return Err(CitygenError::InvalidConfig {
+            field: "cell_size".to_string(),
+            message: format!(
+                "gives a {} by {} grid, more than {} cells",
+                columns, rows, MAX_POPULATION_CELLS
+            ),
+        });
+    }
+    let (columns, rows) = (columns as usize, rows as usize);
+
+    let mut values = Vec::with_capacity(columns * rows);
+    for row in 0..rows {
+        let y = extent.max_y - (row as f64 + 0.5) * cell_size;
+        for column in 0..columns {
+            let x = extent.min_x + (column as f64 + 0.5) * cell_size;
+            values.push(population.population_at(x, y));
+        }
+    }
+
+    let grid_extent = Extent {
+        min_x: extent.min_x,
+        min_y: extent.max_y - rows as f64 * cell_size,
+        max_x: extent.min_x + columns as f64 * cell_size,
+        max_y: extent.max_y,
+    };
+
+    Ok(Raster::new(columns, rows, values, grid_extent))
+}
+
+pub fn population_to_geojson(grid: &Raster) -> GeoJson {
+    let cell_width = grid.extent.width() / grid.width as f64;
+    let cell_height = grid.extent.height() / grid.height as f64;
+    let mut features = Vec::new();
+
+    for row in 0..grid.height {
+        let max_y = grid.extent.max_y - row as f64 * cell_height;
+        let min_y = max_y - cell_height;
+
+        for column in 0..grid.width {
+            let min_x = grid.extent.min_x + column as f64 * cell_width;
+            let max_x = min_x + cell_width;
+
+            let ring = vec![
+                vec![min_x, min_y],
+                vec![max_x, min_y],
+                vec![max_x, max_y],
+                vec![min_x, max_y],
+                vec![min_x, min_y],
+            ];
+
+            let mut props = Map::new();
+            let population_val = serde_json::Number::from_f64(grid.pixel(column, row));
+            props.insert("population".to_string(), population_val.into());
+
+            features.push(Feature {
+                bbox: None,
+                geometry: Some(Geometry::new(Value::Polygon(vec![ring]))),
+                id: None,
+                properties: Some(props),
+                foreign_members: None,
+            });
+        }
+    }
+
+    feature_collection(features)
+}
+
+pub fn population_to_pgm(grid: &Raster) -> Vec<u8> {
+    let mut bytes = format!("P5\n{} {}\n255\n", grid.width, grid.height).into_bytes();
+
+    for row in 0..grid.height {
+        for column in 0..grid.width {
+            let value = grid.pixel(column, row).clamp(0.0, 1.0);
+            bytes.push((value * 255.0).round() as u8);
+        }
+    }
+
+    bytes
+}
+
+/// Formats the grid as an ESRI ASCII grid. Assumes square cells.
+pub fn population_to_ascii(grid: &Raster) -> String {
+    let cell_size = grid.extent.width() / grid.width as f64;
+    let mut ascii = format!(
+        "ncols {}\nnrows {}\nxllcorner {}\nyllcorner {}\ncellsize {}\n",
+        grid.width, grid.height, grid.extent.min_x, grid.extent.min_y, cell_size
+    );
+
+    for row in 0..grid.height {
+        let values: Vec<String> = (0..grid.width)
+            .map(|column| grid.pixel(column, row).to_string())
+            .collect();
+        ascii.push_str(&values.join(" "));
+        ascii.push('\n');
+    }
+
+    ascii
+}
+
+/// Samples the population over `extent` and writes it to `path`.
+pub fn write_population(
+    population: &dyn PopulationMap,
+    extent: Extent,
+    cell_size: f64,
+    format: PopulationFormat,
+    path: &Path,
+) -> Result<()> {
+    let grid = sample_population(population, extent, cell_size)?;
+
+    let bytes = match format {
+        PopulationFormat::GeoJson => population_to_geojson(&grid).to_string().into_bytes(),
+        PopulationFormat::Pgm => population_to_pgm(&grid),
+        PopulationFormat::Ascii => population_to_ascii(&grid).into_bytes(),
+    };
+
+    let mut file = File::create(path)?;
+    file.write_all(&bytes)?;
+
+    Ok(())
+}
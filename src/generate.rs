@@ -1,13 +1,30 @@
-use geo::Point;
-use noise::{NoiseFn, Simplex};
+use std::{
+    cmp::Ordering,
+    collections::{BinaryHeap, HashMap},
+    f64::consts::PI,
+};
+
+use geo::{BoundingRect, Contains, Point, Polygon};
+use rand::{rngs::StdRng, Rng, SeedableRng};
 
 use super::{
+    building::{place_buildings, Building},
+    collision::CollisionLimits,
     config::*,
+    error::{CitygenError, Result},
+    graph::RoadGraph,
     math::IntersectionResult,
     math::*,
+    network::ExistingRoad,
+    obstacle::{Obstacle, ObstacleKind},
+    pattern::{GrowthPattern, PatternMap},
+    population::{PopulationMap, SimplexPopulation},
     quad_tree::Quadtree,
+    road_class::RoadClass,
     segment::{CollisionMetaInfo, Road, Segment, SegmentDirection},
+    terrain::ElevationMap,
     utility::*,
+    validate::{validate_links, LinkViolation},
 };
 
 struct LocalConstraints {
@@ -24,50 +41,490 @@ enum LocalConstraintsArgument {
 }
 
 enum LocalConstraintsAction {
-    IntersectionAction,
-    SnapAction,
-    RadiusAction,
+    Intersection,
+    Snap,
+    Radius,
 }
 
 pub struct GlobalConfig {
-    pub segments: Vec<Segment>,
+    /// Accepted segments. Only `push_segment` adds to them, so that they can
+    /// always be looked up by id.
+    segments: Vec<Segment>,
     pub last_id: usize,
-    pub quad_tree: Quadtree,
-    simplex: Simplex,
+    /// Positions of the accepted segments in `segments`, by bounding box.
+    pub quad_tree: Quadtree<usize>,
+    pub config: GenerationConfig,
+    pub population: Box<dyn PopulationMap>,
+    pub obstacles: Vec<Obstacle>,
+    /// Layout of normal roads by region, `config.growth_pattern` everywhere
+    /// unless replaced.
+    pub pattern: Box<dyn PatternMap>,
+    /// Ground height, or `None` for a flat plane.
+    pub elevation: Option<Box<dyn ElevationMap>>,
+    /// Border the network is clipped to, e.g. a municipal boundary. It must
+    /// contain the start of every seed segment.
+    pub boundary: Option<Polygon<f64>>,
+    /// Roads of an existing town, accepted as they are before growth starts.
+    pub existing_roads: Vec<ExistingRoad>,
+    /// Position of every segment in `segments`, by id.
+    segment_indices: HashMap<usize, usize>,
+    rng: StdRng,
+}
+
+/// A segment waiting in `generate_main`'s queue.
+///
+/// Ordered so that the `BinaryHeap` pops the lowest `t` first and, among
+/// equal ones, the segment queued first.
+struct QueuedSegment {
+    order: usize,
+    segment: Segment,
+}
+
+impl Ord for QueuedSegment {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other
+            .segment
+            .t
+            .total_cmp(&self.segment.t)
+            .then_with(|| other.order.cmp(&self.order))
+    }
+}
+
+impl PartialOrd for QueuedSegment {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for QueuedSegment {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for QueuedSegment {}
+
+/// Distance kept between the end of a bridge and the shore, so that the
+/// next segment starts on dry land.
+const SHORE_CLEARANCE: f64 = 1.0;
+
+/// Number of stretches a segment is divided into when measuring its slope,
+/// so that a hill between two level endpoints still counts.
+const SLOPE_SAMPLES: usize = 4;
+
+/// Whether `point` lies on the end (`Some(true)`) or the start
+/// (`Some(false)`) of `road`.
+fn endpoint_at(road: &Segment, point: Point<f64>) -> Option<bool> {
+    if equal_v(point, road.r.end) {
+        Some(true)
+    } else if equal_v(point, road.r.start) {
+        Some(false)
+    } else {
+        None
+    }
 }
 
 impl GlobalConfig {
+    /// Sets up an empty network with the default simplex population map.
+    /// Replace `population` before calling `generate_main` to steer growth.
+    pub fn new(seed: u32, config: &GenerationConfig) -> Result<Self> {
+        config.validate()?;
+
+        Ok(GlobalConfig {
+            segments: Vec::new(),
+            last_id: 0,
+            quad_tree: Quadtree::from_config(),
+            config: config.clone(),
+            population: Box::new(SimplexPopulation::new(seed)),
+            obstacles: Vec::new(),
+            pattern: Box::new(config.growth_pattern),
+            elevation: None,
+            boundary: None,
+            existing_roads: Vec::new(),
+            segment_indices: HashMap::new(),
+            rng: StdRng::seed_from_u64(seed as u64),
+        })
+    }
+
     fn pop_on_road(&self, r: &Road) -> f64 {
         (self.population_at(r.start.x(), r.start.y()) + self.population_at(r.end.x(), r.end.y()))
             / 2.0
     }
 
-    fn population_at(&self, x: f64, y: f64) -> f64 {
-        let value1 = (self.simplex.get([x / 10000.0, y / 10000.0]) + 1.0) / 2.0;
-        let value2 = (self.simplex.get([x / 20000.0 + 500.0, y / 20000.0 + 500.0]) + 1.0) / 2.0;
-        let value3 = (self
-            .simplex
-            .get([x / 20000.0 + 1000.0, y / 20000.0 + 1000.0])
-            + 1.0)
-            / 2.0;
-        f64::powf((value1 * value2 + value3) / 2.0, 2.0)
+    pub fn population_at(&self, x: f64, y: f64) -> f64 {
+        self.population.population_at(x, y)
     }
 
-    fn find_with_index(&mut self, target_id: usize) -> (Option<Segment>, Option<usize>) {
-        let segments_iter = self.segments.clone();
-        let (mut segment_opt, mut segment_index) = (None, None);
+    /// The segments accepted so far, in the order they were accepted.
+    pub fn segments(&self) -> &[Segment] {
+        &self.segments
+    }
+
+    /// Appends an accepted segment and records where to find it.
+    pub fn push_segment(&mut self, segment: Segment) {
+        self.segment_indices.insert(segment.id, self.segments.len());
+        self.segments.push(segment);
+    }
+
+    /// Position of the segment with `id` in `segments`.
+    pub fn segment_index(&self, id: usize) -> Result<usize> {
+        self.segment_indices
+            .get(&id)
+            .copied()
+            .ok_or(CitygenError::MissingSegment(id))
+    }
+
+    pub fn segment(&self, id: usize) -> Result<&Segment> {
+        Ok(&self.segments[self.segment_index(id)?])
+    }
+
+    pub fn segment_mut(&mut self, id: usize) -> Result<&mut Segment> {
+        let index = self.segment_index(id)?;
+        Ok(&mut self.segments[index])
+    }
 
-        for (index, segment) in segments_iter.into_iter().enumerate() {
-            if segment.id == target_id {
-                segment_opt = Some(segment);
-                segment_index = Some(index);
+    /// Steepest rise over run along `r`, or `0.0` on flat ground.
+    pub fn slope_of(&self, r: &Road) -> f64 {
+        let Some(elevation) = &self.elevation else {
+            return 0.0;
+        };
+
+        let step = mult_v_scalar(subtract_points(r.end, r.start), 1.0 / SLOPE_SAMPLES as f64);
+        let run = length_v(step);
+        if run < EPSILON {
+            return 0.0;
+        }
+
+        let mut point = r.start;
+        let mut height = elevation.elevation_at(point.x(), point.y());
+        let mut steepest: f64 = 0.0;
+
+        for _ in 0..SLOPE_SAMPLES {
+            point = add_points(point, step);
+            let next_height = elevation.elevation_at(point.x(), point.y());
+            steepest = steepest.max((next_height - height).abs() / run);
+            height = next_height;
+        }
+
+        steepest
+    }
+
+    /// Keeps `segment` if it is within `max_slope`, otherwise turns it step by
+    /// step towards the contour lines until it is. Returns `None` when even
+    /// a right-angle turn is too steep.
+    fn fit_to_terrain(&self, mut segment: Segment) -> Option<Segment> {
+        if self.elevation.is_none() || self.slope_of(&segment.r) <= self.config.max_slope {
+            return Some(segment);
+        }
+
+        let dir = segment.dir().unwrap();
+        let len = segment.length().unwrap();
+        let step = self.config.slope_angle_step;
+        let steps = (90.0 / step).floor() as usize;
+
+        // the smallest turn that works wins, the gentler side on a tie
+        for k in 1..=steps {
+            let mut best: Option<(f64, Segment)> = None;
+
+            for side in [-1.0, 1.0] {
+                let candidate = Segment::using_direction(
+                    &segment.r.start,
+                    Some(&(dir + side * k as f64 * step)),
+                    Some(&len),
+                    &segment.t,
+                    segment.q,
+                    &segment.id,
+                    &self.config,
+                );
+                let slope = self.slope_of(&candidate.r);
+
+                if slope <= self.config.max_slope
+                    && best
+                        .as_ref()
+                        .is_none_or(|(best_slope, _)| slope < *best_slope)
+                {
+                    best = Some((slope, candidate));
+                }
+            }
+
+            if let Some((_, candidate)) = best {
+                return Some(candidate);
             }
         }
 
-        (segment_opt, segment_index)
+        None
     }
 
-    fn local_constraints(&mut self, segment: &mut Segment) -> bool {
+    /// Truncates `segment` where it first enters an obstacle, or lets a major road
+    /// bridge narrow water, extending it to the far shore if needed. Returns
+    /// `false` when the segment should be discarded.
+    fn obstacle_constraints(&self, segment: &mut Segment) -> bool {
+        if self.obstacles.is_empty() {
+            return true;
+        }
+
+        let start = segment.r.start;
+        let segment_length = length(start, segment.r.end);
+        let direction = mult_v_scalar(subtract_points(segment.r.end, start), 1.0 / segment_length);
+        let max_bridge = self.config.max_bridge_length;
+        let can_bridge = segment.q.class.is_major() && max_bridge > 0.0;
+
+        // major roads look past their end for the far shore of water they enter
+        let probe_length = if can_bridge {
+            segment_length + max_bridge + SHORE_CLEARANCE
+        } else {
+            segment_length
+        };
+        let probe_end = add_points(start, mult_v_scalar(direction, probe_length));
+
+        let mut blocked_at: Option<f64> = None;
+        let mut required_length = segment_length;
+
+        for obstacle in self.obstacles.iter() {
+            let (min, max) = (obstacle.bounds.min(), obstacle.bounds.max());
+            if f64::max(start.x(), probe_end.x()) < min.x
+                || f64::min(start.x(), probe_end.x()) > max.x
+                || f64::max(start.y(), probe_end.y()) < min.y
+                || f64::min(start.y(), probe_end.y()) > max.y
+            {
+                continue;
+            }
+
+            let mut breaks = vec![0.0];
+            breaks.extend(
+                polygon_crossings(start, probe_end, &obstacle.polygon)
+                    .into_iter()
+                    .map(|t| t * probe_length),
+            );
+            breaks.push(probe_length);
+
+            // classify each stretch between boundary crossings by its midpoint
+            for stretch in breaks.windows(2) {
+                let (from, to) = (stretch[0], stretch[1]);
+                if from >= segment_length {
+                    break;
+                }
+
+                let midpoint = add_points(start, mult_v_scalar(direction, (from + to) / 2.0));
+                if to - from < EPSILON || !obstacle.polygon.contains(&midpoint) {
+                    continue;
+                }
+
+                let reaches_shore = to < probe_length;
+                if can_bridge
+                    && obstacle.kind == ObstacleKind::Water
+                    && reaches_shore
+                    && to - from <= max_bridge
+                {
+                    required_length = required_length.max(to + SHORE_CLEARANCE);
+                } else {
+                    blocked_at = Some(blocked_at.map_or(from, |b| b.min(from)));
+                    break;
+                }
+            }
+        }
+
+        let final_length = match blocked_at {
+            Some(blocked_at) => {
+                if blocked_at < self.config.road_snap_distance {
+                    return false;
+                }
+                segment.q.severed = true;
+                blocked_at
+            }
+            None => required_length,
+        };
+
+        if final_length != segment_length {
+            segment.set_end(&add_points(start, mult_v_scalar(direction, final_length)));
+        }
+
+        true
+    }
+
+    /// Stretches of `road` lying in water, as `(from, to)` fractions of its
+    /// length.
+    fn water_stretches(&self, road: &Road) -> Vec<(f64, f64)> {
+        let (start, end) = (road.start, road.end);
+        let road_length = length(start, end);
+        let mut stretches = Vec::new();
+
+        for obstacle in self.obstacles.iter() {
+            if obstacle.kind != ObstacleKind::Water {
+                continue;
+            }
+
+            let mut breaks = vec![0.0];
+            breaks.extend(polygon_crossings(start, end, &obstacle.polygon));
+            breaks.push(1.0);
+
+            for stretch in breaks.windows(2) {
+                let (from, to) = (stretch[0], stretch[1]);
+                let midpoint = add_points(
+                    start,
+                    mult_v_scalar(subtract_points(end, start), (from + to) / 2.0),
+                );
+                if (to - from) * road_length >= EPSILON && obstacle.polygon.contains(&midpoint) {
+                    stretches.push((from, to));
+                }
+            }
+        }
+
+        stretches
+    }
+
+    /// Whether any part of `road` lies in water, i.e. it has to be a bridge.
+    pub fn crosses_water(&self, road: &Road) -> bool {
+        !self.water_stretches(road).is_empty()
+    }
+
+    /// Whether `road` stops in the middle of water.
+    fn ends_in_water(&self, road: &Road) -> bool {
+        self.water_stretches(road).iter().any(|(_, to)| *to == 1.0)
+    }
+
+    /// Reshapes a new normal road to the growth pattern at its start.
+    /// Major roads are left as they are.
+    fn follow_pattern(&self, segment: Segment) -> Segment {
+        if segment.q.class.is_major() {
+            return segment;
+        }
+
+        let (start, end) = (segment.r.start, segment.r.end);
+        let pattern_end = match self.pattern.pattern_at(start.x(), start.y()) {
+            GrowthPattern::Organic => None,
+            GrowthPattern::Grid => Some(self.grid_end(start, calculate_direction(&segment.r))),
+            GrowthPattern::Radial => self.radial_end(start, end),
+        };
+        let Some(pattern_end) = pattern_end else {
+            return segment;
+        };
+
+        let blend = self
+            .pattern
+            .blend_at(start.x(), start.y())
+            .unwrap_or(self.config.pattern_blend);
+        let end = add_points(
+            mult_v_scalar(end, 1.0 - blend),
+            mult_v_scalar(pattern_end, blend),
+        );
+
+        Segment::new(
+            start,
+            end,
+            &segment.t,
+            &segment.q,
+            &segment.id,
+            &self.config,
+        )
+    }
+
+    /// End of a grid street leaving `start` along the grid axis closest to
+    /// `dir`. It stops on the next cross street, so that streets started
+    /// from different places still line up into blocks.
+    fn grid_end(&self, start: Point<f64>, dir: f64) -> Point<f64> {
+        let orientation = self.config.grid_orientation;
+        let block = self.config.grid_block_size;
+
+        let axis = orientation + ((dir - orientation) / 90.0).round() * 90.0;
+        let unit = Point::new(f64::sin(axis * PI / 180.0), f64::cos(axis * PI / 180.0));
+
+        // position along the axis, measured in blocks from the origin
+        let along = dot_product(start, unit) / block;
+        let mut next_street = along.floor() + 1.0;
+        if next_street - along < 0.5 {
+            next_street += 1.0;
+        }
+
+        add_points(start, mult_v_scalar(unit, (next_street - along) * block))
+    }
+
+    /// End of a road from `start` towards `end` bent onto the nearest centre's
+    /// ring, or turned into a spoke when it already heads mostly towards or
+    /// away from the centre. `None` when there is no centre to orient by.
+    fn radial_end(&self, start: Point<f64>, end: Point<f64>) -> Option<Point<f64>> {
+        let centre = self
+            .config
+            .radial_centres
+            .iter()
+            .map(|centre| Point::new(centre[0], centre[1]))
+            .min_by(|a, b| length(*a, start).total_cmp(&length(*b, start)))?;
+
+        let offset = subtract_points(start, centre);
+        let radius = length_v(offset);
+        if radius < EPSILON {
+            return None;
+        }
+
+        let road_length = length(start, end);
+        let heading = mult_v_scalar(subtract_points(end, start), 1.0 / road_length);
+        let outward = mult_v_scalar(offset, 1.0 / radius);
+
+        let along_spoke = dot_product(heading, outward);
+        let along_ring = cross_product(outward, heading);
+
+        if along_spoke.abs() >= along_ring.abs() {
+            // spokes heading inwards stop at the centre
+            let distance = if along_spoke >= 0.0 {
+                road_length
+            } else {
+                -road_length.min(radius)
+            };
+            return Some(add_points(start, mult_v_scalar(outward, distance)));
+        }
+
+        // rotate around the centre, at most a quarter turn on tight rings
+        let angle = sign(along_ring) * (road_length / radius).min(PI / 2.0);
+        let (sin, cos) = angle.sin_cos();
+        let rotated = Point::new(
+            offset.x() * cos - offset.y() * sin,
+            offset.x() * sin + offset.y() * cos,
+        );
+
+        Some(add_points(centre, rotated))
+    }
+
+    /// Clips `segment` where it leaves the boundary and stops growth there.
+    /// Returns `false` when it starts outside or would be left as a stub.
+    fn boundary_constraints(&self, segment: &mut Segment) -> bool {
+        let Some(boundary) = &self.boundary else {
+            return true;
+        };
+
+        let (start, end) = (segment.r.start, segment.r.end);
+        if !boundary.contains(&start) {
+            return false;
+        }
+
+        let Some(exit) = polygon_crossings(start, end, boundary).into_iter().next() else {
+            return true;
+        };
+
+        if exit * length(start, end) < self.config.road_snap_distance {
+            return false;
+        }
+
+        segment.set_end(&add_points(
+            start,
+            mult_v_scalar(subtract_points(end, start), exit),
+        ));
+        segment.q.severed = true;
+
+        true
+    }
+
+    /// Checks `segment` against nearby roads, adjusting it to intersect or snap
+    /// onto them. Returns `Ok(false)` when the segment should be discarded.
+    fn local_constraints(&mut self, segment: &mut Segment) -> Result<bool> {
+        if !self.obstacle_constraints(segment) || !self.boundary_constraints(segment) {
+            return Ok(false);
+        }
+
+        // e.g. a bridge clipped by the boundary before reaching the far shore
+        if self.ends_in_water(&segment.r) {
+            return Ok(false);
+        }
+
         let mut action = LocalConstraints {
             priority: 0,
             action: None,
@@ -76,93 +533,93 @@ impl GlobalConfig {
             this_road_index: None,
         };
 
-        let matches = self.quad_tree.retrieve(&segment.collider.limits());
-
-        if matches.len() < 1 {
-            return true;
-        }
-
-        for i in 0..matches.len() - 1 {
-            let _match = matches[i];
+        // roads the end may snap to can lie outside the segment's own box
+        let snap_distance = self.config.road_snap_distance;
+        let limits = segment.collider.limits();
+        let area = CollisionLimits {
+            x: limits.x - snap_distance,
+            y: limits.y - snap_distance,
+            width: limits.width + 2.0 * snap_distance,
+            height: limits.height + 2.0 * snap_distance,
+            id: None,
+        };
 
-            let (this_road, this_road_index) = match self.find_with_index(_match.id.unwrap()) {
-                (Some(road), Some(index)) => (road, index),
-                _ => continue,
-            };
+        for &this_road_index in self.quad_tree.retrieve(&area) {
+            let this_road = &self.segments[this_road_index];
 
             if action.priority <= 4 {
                 let intersection = self.do_road_segments_intersect(&segment.r, &this_road.r);
-                if intersection.is_some() {
+                if let Some(intersection) = intersection {
                     action = LocalConstraints {
                         priority: 4,
-                        action: Some(LocalConstraintsAction::IntersectionAction),
-                        other_arg: Some(LocalConstraintsArgument::Intersection(
-                            intersection.unwrap(),
-                        )),
+                        action: Some(LocalConstraintsAction::Intersection),
+                        other_arg: Some(LocalConstraintsArgument::Intersection(intersection)),
                         this_road: Some(this_road.clone()),
-                        this_road_index: Some(this_road_index.clone()),
+                        this_road_index: Some(this_road_index),
                     }
                 }
             };
-            if action.priority <= 3 {
-                if length(segment.r.end, this_road.r.end) < ROAD_SNAP_DISTANCE {
-                    action = LocalConstraints {
-                        priority: 3,
-                        action: Some(LocalConstraintsAction::RadiusAction),
-                        this_road: Some(this_road.clone()),
-                        this_road_index: Some(this_road_index.clone()),
-                        other_arg: None,
-                    }
+            if action.priority <= 3
+                && length(segment.r.end, this_road.r.end) < self.config.road_snap_distance
+            {
+                action = LocalConstraints {
+                    priority: 3,
+                    action: Some(LocalConstraintsAction::Radius),
+                    this_road: Some(this_road.clone()),
+                    this_road_index: Some(this_road_index),
+                    other_arg: None,
                 }
             };
 
             if action.priority <= 2 {
-                let added_point: AddedPoint = distance_to_line(
-                    segment.r.end,
-                    this_road.clone().r.start,
-                    this_road.clone().r.end,
-                );
+                let added_point: AddedPoint =
+                    distance_to_line(segment.r.end, this_road.r.start, this_road.r.end);
 
-                if added_point.distance2 < ROAD_SNAP_DISTANCE * ROAD_SNAP_DISTANCE
-                    && added_point.line_proj_2 >= 0.0
-                    && added_point.line_proj_2 <= added_point.length2
+                if added_point.distance2 < snap_distance * snap_distance
+                    && (0.0..=added_point.length2).contains(&added_point.line_proj_2)
                 {
                     action = LocalConstraints {
                         priority: 2,
-                        action: Some(LocalConstraintsAction::SnapAction),
+                        action: Some(LocalConstraintsAction::Snap),
                         other_arg: Some(LocalConstraintsArgument::Point(added_point)),
                         this_road: Some(this_road.clone()),
-                        this_road_index: Some(this_road_index.clone()),
+                        this_road_index: Some(this_road_index),
                     }
                 }
             }
         }
 
         match action.action {
-            Some(LocalConstraintsAction::IntersectionAction) => {
+            Some(LocalConstraintsAction::Intersection) => {
                 let mut this_road = action.this_road.unwrap();
 
                 if min_degree_difference(this_road.dir().unwrap(), segment.dir().unwrap())
-                    < MINIMUM_INTERSECTION_DEVIATION
+                    < self.config.minimum_intersection_deviation
                 {
-                    return false;
+                    return Ok(false);
                 }
 
                 let intersection = match action.other_arg.unwrap() {
                     LocalConstraintsArgument::Intersection(intersection) => intersection,
-                    LocalConstraintsArgument::Point(_) => return false,
+                    LocalConstraintsArgument::Point(_) => return Ok(false),
                 };
                 let intersection_point = Point::new(intersection.x, intersection.y);
 
-                this_road.split(intersection_point, segment, self);
-                self.segments[action.this_road_index.unwrap()] = this_road.clone();
+                if let Some(at_end) = endpoint_at(&this_road, intersection_point) {
+                    segment.set_end(&intersection_point);
+                    segment.q.severed = true;
+                    return self.join_at_endpoint(segment, this_road.id, at_end);
+                }
+
+                this_road.split(intersection_point, segment, self)?;
+                self.segments[action.this_road_index.unwrap()] = this_road;
 
                 segment.set_end(&intersection_point);
                 segment.q.severed = true;
 
-                return true;
+                return Ok(true);
             }
-            Some(LocalConstraintsAction::RadiusAction) => {
+            Some(LocalConstraintsAction::Radius) => {
                 let mut this_road = action.this_road.unwrap();
                 let point = this_road.r.end;
                 let direction: SegmentDirection;
@@ -170,7 +627,7 @@ impl GlobalConfig {
                 segment.r.end = point;
                 segment.q.severed = true;
 
-                let mut links = if this_road.clone().start_is_backwards(&self.segments) {
+                let mut links = if this_road.start_is_backwards(self)? {
                     direction = SegmentDirection::Forward;
                     this_road.clone().links.f
                 } else {
@@ -178,41 +635,36 @@ impl GlobalConfig {
                     this_road.clone().links.b
                 };
 
-                if links.iter().any(|link_id| {
-                    let link_segment = self.segments.iter().find(|x| &x.id == link_id);
-                    if let Some(link) = link_segment {
-                        (equal_v(link.r.start, segment.r.end)
-                            && equal_v(link.r.end, segment.r.start))
-                            || (equal_v(link.r.start, segment.r.start)
-                                && equal_v(link.r.end, segment.r.end))
-                    } else {
-                        true // CHECK AGAIN!!!
+                // Reject the segment if it duplicates the road it snaps to or
+                // one already linked at this end
+                for link_id in links.iter().chain([&this_road.id]) {
+                    let link = self.segment(*link_id)?;
+
+                    if (equal_v(link.r.start, segment.r.end)
+                        && equal_v(link.r.end, segment.r.start))
+                        || (equal_v(link.r.start, segment.r.start)
+                            && equal_v(link.r.end, segment.r.end))
+                    {
+                        return Ok(false);
                     }
-                }) {
-                    return false;
                 }
 
                 for link_id in links.iter() {
-                    let (link_segment, link_segment_index) = self.find_with_index(*link_id);
-
-                    if link_segment.is_none() {
-                        continue;
-                    }
+                    let link_segment_index = self.segment_index(*link_id)?;
+                    let link_segment = self.segments[link_segment_index].clone();
 
-                    let (vec_to_push, direction) = link_segment
-                        .unwrap()
-                        .clone()
-                        .links_for_end_containing(&this_road.id);
+                    let (vec_to_push, direction) =
+                        link_segment.links_for_end_containing(&this_road.id);
 
                     if let Some(mut link_links) = vec_to_push {
                         link_links.push(segment.id);
 
                         match direction {
                             SegmentDirection::Back => {
-                                self.segments[link_segment_index.unwrap()].links.b = link_links;
+                                self.segments[link_segment_index].links.b = link_links;
                             }
                             SegmentDirection::Forward => {
-                                self.segments[link_segment_index.unwrap()].links.f = link_links;
+                                self.segments[link_segment_index].links.f = link_links;
                             }
                             _ => (),
                         }
@@ -231,12 +683,12 @@ impl GlobalConfig {
 
                 self.segments[action.this_road_index.unwrap()] = this_road;
 
-                return true;
+                return Ok(true);
             }
-            Some(LocalConstraintsAction::SnapAction) => {
+            Some(LocalConstraintsAction::Snap) => {
                 let added_point = match action.other_arg.unwrap() {
                     LocalConstraintsArgument::Point(added_point) => added_point,
-                    LocalConstraintsArgument::Intersection(_) => return false,
+                    LocalConstraintsArgument::Intersection(_) => return Ok(false),
                 };
                 let point = added_point.point_on_line;
 
@@ -245,19 +697,74 @@ impl GlobalConfig {
                 let mut this_road = action.this_road.unwrap();
 
                 if min_degree_difference(this_road.dir().unwrap(), segment.dir().unwrap())
-                    < MINIMUM_INTERSECTION_DEVIATION
+                    < self.config.minimum_intersection_deviation
                 {
-                    return false;
+                    return Ok(false);
                 }
 
-                this_road.split(point, segment, self);
+                // splitting at an endpoint would leave a zero-length piece
+                if let Some(at_end) = endpoint_at(&this_road, point) {
+                    return self.join_at_endpoint(segment, this_road.id, at_end);
+                }
+
+                this_road.split(point, segment, self)?;
                 self.segments[action.this_road_index.unwrap()] = this_road;
 
-                return true;
+                return Ok(true);
             }
             None => (),
         }
-        true
+        Ok(true)
+    }
+
+    /// Ends `segment` at the start or end of the road with `road_id`, linking
+    /// it to that road and every other road meeting there. Returns
+    /// `Ok(false)` when the segment would duplicate one of them.
+    fn join_at_endpoint(
+        &mut self,
+        segment: &mut Segment,
+        road_id: usize,
+        at_end: bool,
+    ) -> Result<bool> {
+        let road = self.segment(road_id)?;
+        let links = if at_end {
+            road.links.f.clone()
+        } else {
+            road.links.b.clone()
+        };
+
+        for link_id in links.iter().chain([&road_id]) {
+            let link = self.segment(*link_id)?;
+
+            if (equal_v(link.r.start, segment.r.end) && equal_v(link.r.end, segment.r.start))
+                || (equal_v(link.r.start, segment.r.start) && equal_v(link.r.end, segment.r.end))
+            {
+                return Ok(false);
+            }
+        }
+
+        for link_id in links.iter() {
+            let link = self.segment_mut(*link_id)?;
+            if link.links.b.contains(&road_id) {
+                link.links.b.push(segment.id);
+            } else if link.links.f.contains(&road_id) {
+                link.links.f.push(segment.id);
+            } else {
+                continue;
+            }
+
+            segment.links.f.push(*link_id);
+        }
+
+        let road = self.segment_mut(road_id)?;
+        if at_end {
+            road.links.f.push(segment.id);
+        } else {
+            road.links.b.push(segment.id);
+        }
+        segment.links.f.push(road_id);
+
+        Ok(true)
     }
 
     fn do_road_segments_intersect(&self, r1: &Road, r2: &Road) -> Option<IntersectionResult> {
@@ -269,106 +776,137 @@ impl GlobalConfig {
 
         if !previous_segment.q.severed {
             let r_end = previous_segment.r.end;
-            let len = previous_segment.length().unwrap();
+            let config = self.config.clone();
+            let class = previous_segment.q.class;
+            let class_config = *config.road_class(class);
+            // bridges are stretched to the far shore, so don't carry their length on
+            let len = if previous_segment.q.bridge {
+                class_config.segment_length
+            } else {
+                previous_segment.length().unwrap()
+            };
             let dir = previous_segment.dir().unwrap();
             let some_dir = Some(&dir);
+            let continue_q = CollisionMetaInfo {
+                bridge: false,
+                ..previous_segment.q
+            };
 
-            // Used for highways or going straight on a normal branch
+            // Used for major roads or going straight on a minor one
             let template_continue = |dir, id| {
-                Segment::using_direction(
-                    &r_end,
-                    dir,
-                    Some(&len),
-                    &0.0,
-                    previous_segment.q.clone(),
-                    id,
-                )
+                Segment::using_direction(&r_end, dir, Some(&len), &0.0, continue_q, id, &config)
             };
 
-            // Not using q, i.e., not highways
-            let template_branch = |dir, id| {
+            // Side roads, which wait before growing when they leave a bigger road
+            let template_branch = |side_class: RoadClass, dir: Option<&f64>, id: &usize| {
+                let side = config.road_class(side_class);
                 Segment::using_direction(
                     &r_end,
                     dir,
-                    Some(&DEFAULT_SEGMENT_LENGTH),
-                    if previous_segment.q.highway == Some(true) {
-                        &NORMAL_BRANCH_TIME_DELAY_FROM_HIGHWAY
-                    } else {
+                    Some(&side.segment_length),
+                    if side_class == class {
                         &0.0
+                    } else {
+                        &side.time_delay
                     },
-                    CollisionMetaInfo::new(),
+                    CollisionMetaInfo::new(side_class),
                     id,
+                    &config,
                 )
             };
 
-            let _id = self.last_id.clone();
+            let _id = self.last_id;
             let mut continue_straight = template_continue(some_dir, &_id);
             let straight_pop = self.pop_on_road(&continue_straight.r);
+            let (mut left_taken, mut right_taken) = (false, false);
 
-            if previous_segment.q.highway == Some(true) {
-                let _random_angle = &dir + random_angle(AngleDirection::Forward);
+            if class.is_major() {
+                let _random_angle =
+                    dir + random_angle(AngleDirection::Forward, &config, &mut self.rng);
                 let random_angle_some = Some(&(_random_angle));
 
                 let mut random_straight = template_continue(random_angle_some, &_id);
 
                 let random_pop = self.pop_on_road(&random_straight.r);
-                let road_pop: f64;
 
-                if random_pop > straight_pop {
+                let road_pop = if random_pop > straight_pop {
                     self.last_id += 1;
                     random_straight.set_id(self.last_id);
 
                     new_branches.push(random_straight);
-                    road_pop = random_pop
+                    random_pop
                 } else {
                     self.last_id += 1;
                     continue_straight.set_id(self.last_id);
 
                     new_branches.push(continue_straight.clone());
-                    road_pop = straight_pop;
-                }
+                    straight_pop
+                };
 
-                if road_pop > HIGHWAY_BRANCH_POPULATION_THRESHOLD {
-                    if rand::random::<f64>() < HIGHWAY_BRANCH_PROBABILITY {
+                // major roads fork into two roads of their own class
+                if road_pop > class_config.branch_population_threshold {
+                    if self.rng.gen::<f64>() < class_config.branch_probability {
+                        left_taken = true;
                         self.last_id += 1;
 
-                        let left_highway_branch_angle =
-                            &dir - 90.0 + random_angle(AngleDirection::Branch);
-                        let left_highway_branch =
-                            template_continue(Some(&left_highway_branch_angle), &self.last_id);
-                        new_branches.push(left_highway_branch);
-                    } else if rand::random::<f64>() < HIGHWAY_BRANCH_PROBABILITY {
+                        let left_fork_angle = dir - 90.0
+                            + random_angle(AngleDirection::Branch, &config, &mut self.rng);
+                        let left_fork = template_continue(Some(&left_fork_angle), &self.last_id);
+                        new_branches.push(left_fork);
+                    } else if self.rng.gen::<f64>() < class_config.branch_probability {
+                        right_taken = true;
                         self.last_id += 1;
 
-                        let right_highway_branch_angle =
-                            &dir + 90.0 + random_angle(AngleDirection::Branch);
-                        let right_highway_branch =
-                            template_continue(Some(&right_highway_branch_angle), &self.last_id);
-                        new_branches.push(right_highway_branch);
+                        let right_fork_angle = dir
+                            + 90.0
+                            + random_angle(AngleDirection::Branch, &config, &mut self.rng);
+                        let right_fork = template_continue(Some(&right_fork_angle), &self.last_id);
+                        new_branches.push(right_fork);
                     }
                 }
-            } else if straight_pop > NORMAL_BRANCH_POPULATION_THRESHOLD {
+            } else if straight_pop > class_config.branch_population_threshold {
                 self.last_id += 1;
                 continue_straight.set_id(self.last_id);
 
                 new_branches.push(continue_straight);
             }
 
-            if straight_pop > NORMAL_BRANCH_POPULATION_THRESHOLD {
-                if rand::random::<f64>() < DEFAULT_BRANCH_PROBABILITY {
-                    let left_branch_angle = &dir - 90.0 + random_angle(AngleDirection::Branch);
+            // minor roads branch into their own class, and every road into the
+            // next smaller one, with at most one road leaving on either side
+            let side_classes = [(!class.is_major()).then_some(class), class.smaller()];
+
+            for side_class in side_classes.into_iter().flatten() {
+                let side = config.road_class(side_class);
+                if straight_pop <= side.branch_population_threshold {
+                    continue;
+                }
+
+                if !left_taken && self.rng.gen::<f64>() < side.branch_probability {
+                    left_taken = true;
+                    let left_branch_angle =
+                        dir - 90.0 + random_angle(AngleDirection::Branch, &config, &mut self.rng);
                     self.last_id += 1;
-                    let left_branch = template_branch(Some(&(left_branch_angle)), &self.last_id);
+                    let left_branch =
+                        template_branch(side_class, Some(&(left_branch_angle)), &self.last_id);
                     new_branches.push(left_branch);
-                } else if rand::random::<f64>() < DEFAULT_BRANCH_PROBABILITY {
-                    let right_branch_angle = &dir + 90.0 + random_angle(AngleDirection::Branch);
+                } else if !right_taken && self.rng.gen::<f64>() < side.branch_probability {
+                    right_taken = true;
+                    let right_branch_angle =
+                        dir + 90.0 + random_angle(AngleDirection::Branch, &config, &mut self.rng);
                     self.last_id += 1;
-                    let right_branch = template_branch(Some(&(right_branch_angle)), &self.last_id);
+                    let right_branch =
+                        template_branch(side_class, Some(&(right_branch_angle)), &self.last_id);
                     new_branches.push(right_branch);
                 }
             }
         }
 
+        let mut new_branches: Vec<Segment> = new_branches
+            .into_iter()
+            .map(|branch| self.follow_pattern(branch))
+            .filter_map(|branch| self.fit_to_terrain(branch))
+            .collect();
+
         for branch in new_branches.iter_mut() {
             branch.prev_segment_to_link = Some(previous_segment.id);
         }
@@ -376,71 +914,203 @@ impl GlobalConfig {
         new_branches
     }
 
-    fn generate_main(&mut self) -> (Vec<Segment>, Quadtree) {
-        let mut priority_q: Vec<Segment> = Vec::new();
+    /// Adds `existing_roads` to the network, linked wherever their ends meet.
+    fn accept_existing_roads(&mut self) {
+        let first = self.segments.len();
+
+        for existing in self.existing_roads.clone() {
+            self.last_id += 1;
+            let q = CollisionMetaInfo::new(existing.class);
+
+            let segment = Segment::new(
+                existing.road.start,
+                existing.road.end,
+                &0.0,
+                &q,
+                &self.last_id,
+                &self.config,
+            );
+            self.push_segment(segment);
+        }
+
+        let graph = RoadGraph::from_segments(&self.segments[first..]);
+
+        for (index, (segment, edge)) in self.segments[first..]
+            .iter_mut()
+            .zip(graph.edges.iter())
+            .enumerate()
+        {
+            let id = segment.id;
+            let others = |node: usize| -> Vec<usize> {
+                graph.nodes[node]
+                    .segments
+                    .iter()
+                    .copied()
+                    .filter(|other| *other != id)
+                    .collect()
+            };
 
-        self.last_id += 1;
-        // Setup first segments in queue
-        let mut root_segment = Segment::new(
-            Point::new(0.0, 0.0),
-            Point::new(HIGHWAY_SEGMENT_LENGTH, 0.0),
-            &0.0,
-            &CollisionMetaInfo {
-                highway: Some(true),
-                severed: false,
-            },
-            &self.last_id,
-        );
+            segment.links.b = others(edge.start);
+            segment.links.f = others(edge.end);
+            self.quad_tree
+                .insert(segment.collider.limits(), first + index);
+        }
+    }
 
-        self.last_id += 1;
-        let mut opposite_direction =
-            Segment::from_existing(&root_segment, None, None, None, &self.last_id);
+    /// The configured seed segments, or two motorways heading east and west
+    /// from the origin.
+    fn seed_segments(&mut self) -> Vec<Segment> {
+        let motorway = CollisionMetaInfo::new(RoadClass::Motorway);
+        let motorway_length = self.config.motorway.segment_length;
+
+        if self.config.seeds.is_empty() {
+            self.last_id += 1;
+            let root_segment = Segment::new(
+                Point::new(0.0, 0.0),
+                Point::new(motorway_length, 0.0),
+                &0.0,
+                &motorway,
+                &self.last_id,
+                &self.config,
+            );
+
+            self.last_id += 1;
+            let mut opposite_direction = Segment::from_existing(
+                &root_segment,
+                None,
+                None,
+                None,
+                &self.last_id,
+                &self.config,
+            );
+            opposite_direction.set_end(&Point::new(
+                root_segment.r.start.x() - motorway_length,
+                root_segment.r.start.y(),
+            ));
+
+            return vec![root_segment, opposite_direction];
+        }
 
-        let new_end = Point::new(
-            root_segment.r.start.x() - HIGHWAY_SEGMENT_LENGTH,
-            opposite_direction.r.end.y(),
-        );
+        let mut seeds = Vec::new();
+
+        for seed in self.config.seeds.iter() {
+            let q = CollisionMetaInfo::new(seed.class);
+            let length = self.config.road_class(seed.class).segment_length;
+
+            self.last_id += 1;
+            seeds.push(Segment::using_direction(
+                &Point::new(seed.position[0], seed.position[1]),
+                Some(&seed.direction),
+                Some(&length),
+                &0.0,
+                q,
+                &self.last_id,
+                &self.config,
+            ));
+        }
 
-        opposite_direction.set_end(&new_end);
-        opposite_direction.links.b.push(root_segment.id);
-        root_segment.links.b.push(opposite_direction.id);
+        seeds
+    }
 
-        priority_q.push(root_segment);
-        priority_q.push(opposite_direction);
+    /// Fails with `InvalidConfig` naming the first seed that starts outside the
+    /// boundary, since nothing could grow from it.
+    fn check_seeds_in_boundary(&self) -> Result<()> {
+        let Some(boundary) = &self.boundary else {
+            return Ok(());
+        };
 
-        while priority_q.len() > 0 && self.segments.len() < SEGMENT_COUNT_LIMIT as usize {
-            let mut min_t: Option<f64> = None;
-            let mut min_t_i: usize = 0;
+        if self.config.seeds.is_empty() && !boundary.contains(&Point::new(0.0, 0.0)) {
+            return Err(CitygenError::InvalidConfig {
+                field: "seeds".to_string(),
+                message: "is empty, and the default seeds at the origin lie outside the boundary"
+                    .to_string(),
+            });
+        }
 
-            for (index, segment) in priority_q.iter().enumerate() {
-                if min_t.is_none() || segment.t < min_t.unwrap() {
-                    min_t = Some(segment.t);
-                    min_t_i = index;
-                }
+        for (index, seed) in self.config.seeds.iter().enumerate() {
+            let [x, y] = seed.position;
+            if !boundary.contains(&Point::new(x, y)) {
+                return Err(CitygenError::InvalidConfig {
+                    field: format!("seeds[{}].position", index),
+                    message: format!("({}, {}) lies outside the boundary", x, y),
+                });
             }
+        }
+
+        Ok(())
+    }
+
+    /// Links a seed segment to the accepted roads that meet its start.
+    fn link_seed(&mut self, seed: &mut Segment) {
+        for segment in self.segments.iter_mut() {
+            let links = if equal_v(segment.r.start, seed.r.start) {
+                &mut segment.links.b
+            } else if equal_v(segment.r.end, seed.r.start) {
+                &mut segment.links.f
+            } else {
+                continue;
+            };
+
+            links.push(seed.id);
+            seed.links.b.push(segment.id);
+        }
+    }
 
-            let mut min_segment = priority_q.remove(min_t_i);
-            let accepted = self.local_constraints(&mut min_segment);
+    pub fn generate_main(&mut self) -> Result<(Vec<Segment>, Quadtree<usize>)> {
+        let mut priority_q: BinaryHeap<QueuedSegment> = BinaryHeap::new();
+        let mut queued_count = 0;
+        let mut enqueue = |priority_q: &mut BinaryHeap<QueuedSegment>, segment: Segment| {
+            priority_q.push(QueuedSegment {
+                order: queued_count,
+                segment,
+            });
+            queued_count += 1;
+        };
+
+        self.check_seeds_in_boundary()?;
+
+        if let Some(bounds) = self.boundary.as_ref().and_then(|b| b.bounding_rect()) {
+            // leave room for roads snapping onto the border
+            let margin = self.config.road_snap_distance;
+            self.quad_tree = Quadtree::with_bounds(CollisionLimits {
+                x: bounds.min().x - margin,
+                y: bounds.min().y - margin,
+                width: bounds.width() + 2.0 * margin,
+                height: bounds.height() + 2.0 * margin,
+                id: None,
+            });
+        }
+
+        self.accept_existing_roads();
+        let segment_count_limit = self.segments.len() + self.config.segment_count_limit;
+
+        // Setup first segments in queue
+        for segment in self.seed_segments() {
+            enqueue(&mut priority_q, segment);
+        }
+
+        while self.segments.len() < segment_count_limit {
+            let Some(QueuedSegment {
+                segment: mut min_segment,
+                ..
+            }) = priority_q.pop()
+            else {
+                break;
+            };
+            let accepted = self.local_constraints(&mut min_segment)?;
 
             if accepted {
+                // only now is the geometry final
+                min_segment.q.bridge = self.crosses_water(&min_segment.r);
+
                 if let Some(prev_segment_id) = min_segment.prev_segment_to_link {
-                    let (prev_segment_opt, prev_segment_index) =
-                        self.find_with_index(prev_segment_id);
-                    let mut prev_segment = prev_segment_opt.unwrap();
+                    let prev_segment_index = self.segment_index(prev_segment_id)?;
+                    let mut prev_segment = self.segments[prev_segment_index].clone();
 
-                    for link_id in prev_segment.clone().links.f.clone() {
+                    for link_id in prev_segment.links.f.clone() {
                         min_segment.links.b.push(link_id);
 
-                        let (mut link_opt, mut link_index) = (None, None);
-
-                        for (index, segment) in self.segments.iter_mut().enumerate() {
-                            if segment.id == link_id {
-                                link_opt = Some(segment);
-                                link_index = Some(index);
-                            }
-                        }
-
-                        let link = link_opt.unwrap();
+                        let link = self.segment_mut(link_id)?;
 
                         let (changed_vec_opt, segment_direction) =
                             link.clone().links_for_end_containing(&prev_segment_id);
@@ -449,46 +1119,272 @@ impl GlobalConfig {
                             changed_vec.push(min_segment.id);
 
                             match segment_direction {
-                                SegmentDirection::Back => link.links.b = changed_vec.clone(),
-                                SegmentDirection::Forward => link.links.f = changed_vec.clone(),
+                                SegmentDirection::Back => link.links.b = changed_vec,
+                                SegmentDirection::Forward => link.links.f = changed_vec,
                                 _ => (),
                             }
-
-                            self.segments[link_index.unwrap()] = link.clone();
                         }
                     }
 
                     prev_segment.links.f.push(min_segment.id);
                     min_segment.links.b.push(prev_segment.id);
-                    self.segments[prev_segment_index.unwrap()] = prev_segment.clone();
+                    self.segments[prev_segment_index] = prev_segment;
+                } else {
+                    self.link_seed(&mut min_segment);
                 }
 
                 let branches = self.generate_segments(&mut min_segment);
                 for mut new_segment in branches {
-                    new_segment.t = min_segment.t + 1.0 + new_segment.t;
-                    priority_q.push(new_segment);
+                    new_segment.t += min_segment.t + 1.0;
+                    enqueue(&mut priority_q, new_segment);
+                }
+
+                self.quad_tree
+                    .insert(min_segment.collider.limits(), self.segments.len());
+                self.push_segment(min_segment);
+
+                if self.config.check_links_each_step {
+                    // links to queued segments are expected until they are accepted
+                    let violations: Vec<LinkViolation> = validate_links(&self.segments)
+                        .into_iter()
+                        .filter(|violation| match violation {
+                            LinkViolation::Dangling { link, .. } => {
+                                !priority_q.iter().any(|queued| queued.segment.id == *link)
+                            }
+                            _ => true,
+                        })
+                        .collect();
+                    if !violations.is_empty() {
+                        return Err(CitygenError::InconsistentLinks(violations));
+                    }
                 }
+            }
+        }
 
-                self.quad_tree.insert(min_segment.collider.limits());
-                self.segments.push(min_segment);
+        if let Some(elevation) = &self.elevation {
+            for segment in self.segments.iter_mut() {
+                segment.elevation = Some([
+                    elevation.elevation_at(segment.r.start.x(), segment.r.start.y()),
+                    elevation.elevation_at(segment.r.end.x(), segment.r.end.y()),
+                ]);
             }
         }
 
-        return (self.segments.clone(), self.quad_tree.clone());
+        Ok((self.segments.clone(), self.quad_tree.clone()))
     }
+
+    /// Lines the generated roads with building footprints, denser where the
+    /// population is higher. Lots inside obstacles or outside the boundary
+    /// stay empty.
+    pub fn place_buildings(&mut self) -> Vec<Building> {
+        let density_at = |center: Point<f64>| {
+            let outside = self
+                .boundary
+                .as_ref()
+                .is_some_and(|boundary| !boundary.contains(&center));
+            let blocked = self.obstacles.iter().any(|obstacle| {
+                obstacle.bounds.contains(&center) && obstacle.polygon.contains(&center)
+            });
+
+            if outside || blocked {
+                0.0
+            } else {
+                self.population.population_at(center.x(), center.y())
+            }
+        };
+
+        place_buildings(&self.segments, &self.config, &density_at, &mut self.rng)
+    }
+}
+
+/// Grows a road network from `seed`.
+///
+/// Both the population noise and every random choice are driven by the seed,
+/// so the same seed and config always produce the same network.
+pub fn generate(seed: u32, config: &GenerationConfig) -> Result<(Vec<Segment>, Quadtree<usize>)> {
+    GlobalConfig::new(seed, config)?.generate_main()
 }
 
-pub fn generate() -> (Vec<Segment>, Quadtree) {
-    let quad_tree = Quadtree::from_config();
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    type Layout = Vec<(usize, [f64; 4], Vec<usize>, Vec<usize>)>;
+
+    /// Endpoints and links of every segment, in order.
+    fn layout(segments: &[Segment]) -> Layout {
+        segments
+            .iter()
+            .map(|segment| {
+                let (start, end) = (segment.r.start, segment.r.end);
+                (
+                    segment.id,
+                    [start.x(), start.y(), end.x(), end.y()],
+                    segment.links.b.clone(),
+                    segment.links.f.clone(),
+                )
+            })
+            .collect()
+    }
+
+    fn network(seed: u32) -> Vec<Segment> {
+        let config = GenerationConfig {
+            segment_count_limit: 100,
+            ..GenerationConfig::default()
+        };
+        generate(seed, &config).unwrap().0
+    }
+
+    #[test]
+    fn same_seed_gives_same_network() {
+        assert_eq!(layout(&network(7)), layout(&network(7)));
+    }
+
+    #[test]
+    fn different_seeds_give_different_networks() {
+        assert_ne!(layout(&network(7)), layout(&network(8)));
+    }
+
+    /// A river running north to south between x 1000 and 1100.
+    fn river_network(boundary: Option<Polygon<f64>>) -> Vec<Segment> {
+        let config = GenerationConfig {
+            segment_count_limit: 400,
+            ..GenerationConfig::default()
+        };
+        let river = Polygon::new(
+            vec![
+                (1000.0, -5000.0),
+                (1100.0, -5000.0),
+                (1100.0, 5000.0),
+                (1000.0, 5000.0),
+            ]
+            .into(),
+            Vec::new(),
+        );
+
+        let mut global_config = GlobalConfig::new(42, &config).unwrap();
+        global_config.obstacles = vec![Obstacle::new(ObstacleKind::Water, river).unwrap()];
+        global_config.boundary = boundary;
+        global_config.generate_main().unwrap().0
+    }
+
+    /// Whether some of `segment` lies strictly between x 1000 and 1100.
+    fn over_river(segment: &Segment) -> bool {
+        let (start, end) = (segment.r.start.x(), segment.r.end.x());
+        start.max(end) > 1000.0 + 1e-6 && start.min(end) < 1100.0 - 1e-6
+    }
+
+    #[test]
+    fn only_segments_over_water_are_bridges() {
+        let segments = river_network(None);
+
+        assert!(segments.iter().any(|segment| segment.q.bridge));
+        for segment in segments.iter() {
+            assert_eq!(
+                segment.q.bridge,
+                over_river(segment),
+                "segment {}",
+                segment.id
+            );
+        }
+    }
+
+    #[test]
+    fn bridges_cut_by_the_boundary_are_dropped() {
+        let boundary = Polygon::new(
+            vec![
+                (-6000.0, -6000.0),
+                (1050.0, -6000.0),
+                (1050.0, 6000.0),
+                (-6000.0, 6000.0),
+            ]
+            .into(),
+            Vec::new(),
+        );
+
+        for segment in river_network(Some(boundary)).iter() {
+            assert!(!over_river(segment), "segment {}", segment.id);
+        }
+    }
 
-    let seed: u32 = 42;
-    let simplex = Simplex::new(seed);
+    #[test]
+    fn seed_outside_boundary_is_a_config_error() {
+        let config = GenerationConfig {
+            seeds: vec![SeedSegment {
+                position: [5000.0, 0.0],
+                direction: 0.0,
+                class: RoadClass::Arterial,
+            }],
+            ..GenerationConfig::default()
+        };
+        let mut global_config = GlobalConfig::new(1, &config).unwrap();
+        global_config.boundary = Some(Polygon::new(
+            vec![(-1000.0, -1000.0), (1000.0, -1000.0), (1000.0, 1000.0)].into(),
+            Vec::new(),
+        ));
+
+        match global_config.generate_main() {
+            Err(CitygenError::InvalidConfig { field, .. }) => {
+                assert_eq!(field, "seeds[0].position")
+            }
+            _ => panic!("expected an InvalidConfig error"),
+        }
+    }
+
+    #[test]
+    fn queue_pops_lowest_t_first_and_ties_in_queue_order() {
+        let config = GenerationConfig::default();
+        let queued = |order: usize, t: f64| QueuedSegment {
+            order,
+            segment: Segment::new(
+                Point::new(0.0, 0.0),
+                Point::new(0.0, 100.0),
+                &t,
+                &CollisionMetaInfo::default(),
+                &order,
+                &config,
+            ),
+        };
 
-    let mut global_goals = GlobalConfig {
-        segments: Vec::new(),
-        last_id: 0,
-        quad_tree,
-        simplex,
-    };
-    global_goals.generate_main()
+        let mut queue = BinaryHeap::new();
+        for (order, t) in [(0, 3.0), (1, 1.0), (2, 2.0), (3, 1.0), (4, 0.0), (5, 1.0)] {
+            queue.push(queued(order, t));
+        }
+
+        let popped: Vec<usize> = std::iter::from_fn(|| queue.pop())
+            .map(|queued| queued.order)
+            .collect();
+        assert_eq!(popped, vec![4, 1, 3, 5, 2, 0]);
+    }
+
+    #[test]
+    fn no_pattern_leaves_zero_length_segments() {
+        for pattern in [
+            GrowthPattern::Organic,
+            GrowthPattern::Grid,
+            GrowthPattern::Radial,
+        ] {
+            // seeds 4 and 9 used to snap grid and radial streets onto the
+            // start of a road, splitting off a zero-length piece
+            for seed in [1, 4, 9] {
+                let config = GenerationConfig {
+                    segment_count_limit: 800,
+                    growth_pattern: pattern,
+                    ..GenerationConfig::default()
+                };
+                let segments = generate(seed, &config).unwrap().0;
+
+                for segment in &segments {
+                    assert!(
+                        !equal_v(segment.r.start, segment.r.end),
+                        "{:?} seed {}: segment {} has zero length",
+                        pattern,
+                        seed,
+                        segment.id
+                    );
+                }
+                assert!(crate::validate::validate_links(&segments).is_empty());
+            }
+        }
+    }
 }
@@ -0,0 +1,129 @@
+use std::collections::HashMap;
+
+use geo::Point;
+
+use super::{config::EPSILON, math::equal_v, segment::Segment};
+
+/// An intersection or dead end where one or more segments meet.
+#[derive(Debug, Clone)]
+pub struct Node {
+    pub id: usize,
+    pub position: Point<f64>,
+    /// Ids of the segments that start or end at this node.
+    pub segments: Vec<usize>,
+}
+
+impl Node {
+    pub fn degree(&self) -> usize {
+        self.segments.len()
+    }
+}
+
+/// A segment expressed as a connection between two nodes.
+#[derive(Debug, Clone)]
+pub struct Edge {
+    pub segment_id: usize,
+    pub start: usize,
+    pub end: usize,
+}
+
+/// Road network topology derived from segment endpoints.
+///
+/// Endpoints that `equal_v` treats as the same point, i.e. closer than
+/// `EPSILON.sqrt()`, are merged into a single node, so node ids are stable
+/// indices into `nodes`.
+#[derive(Debug, Clone, Default)]
+pub struct RoadGraph {
+    pub nodes: Vec<Node>,
+    pub edges: Vec<Edge>,
+    edge_by_segment: HashMap<usize, usize>,
+}
+
+impl RoadGraph {
+    pub fn from_segments(segments: &[Segment]) -> Self {
+        let mut graph = RoadGraph::default();
+        let mut cells: HashMap<(i64, i64), Vec<usize>> = HashMap::new();
+
+        for segment in segments.iter() {
+            let start = graph.node_at(segment.r.start, &mut cells);
+            let end = graph.node_at(segment.r.end, &mut cells);
+
+            graph.nodes[start].segments.push(segment.id);
+            if end != start {
+                graph.nodes[end].segments.push(segment.id);
+            }
+
+            graph.edge_by_segment.insert(segment.id, graph.edges.len());
+            graph.edges.push(Edge {
+                segment_id: segment.id,
+                start,
+                end,
+            });
+        }
+
+        graph
+    }
+
+    pub fn node(&self, id: usize) -> Option<&Node> {
+        self.nodes.get(id)
+    }
+
+    /// The edge built from the segment with `segment_id`.
+    pub fn edge(&self, segment_id: usize) -> Option<&Edge> {
+        self.edge_by_segment
+            .get(&segment_id)
+            .map(|index| &self.edges[*index])
+    }
+
+    /// Ids of the nodes directly connected to `node_id` by a segment.
+    pub fn neighbours(&self, node_id: usize) -> Vec<usize> {
+        let Some(node) = self.node(node_id) else {
+            return Vec::new();
+        };
+
+        node.segments
+            .iter()
+            .filter_map(|segment_id| self.edge(*segment_id))
+            .map(|edge| {
+                if edge.start == node_id {
+                    edge.end
+                } else {
+                    edge.start
+                }
+            })
+            .collect()
+    }
+
+    /// Returns the node at `point`, creating it if no existing node is within
+    /// `EPSILON.sqrt()` of it. Nodes are bucketed in grid cells of that size.
+    fn node_at(&mut self, point: Point<f64>, cells: &mut HashMap<(i64, i64), Vec<usize>>) -> usize {
+        let cell_size = EPSILON.sqrt();
+        let cell = (
+            (point.x() / cell_size).floor() as i64,
+            (point.y() / cell_size).floor() as i64,
+        );
+
+        // a matching point may sit just across a cell border
+        for dx in -1..=1 {
+            for dy in -1..=1 {
+                if let Some(candidates) = cells.get(&(cell.0 + dx, cell.1 + dy)) {
+                    for node_id in candidates.iter() {
+                        if equal_v(self.nodes[*node_id].position, point) {
+                            return *node_id;
+                        }
+                    }
+                }
+            }
+        }
+
+        let id = self.nodes.len();
+        self.nodes.push(Node {
+            id,
+            position: point,
+            segments: Vec::new(),
+        });
+        cells.entry(cell).or_default().push(id);
+
+        id
+    }
+}
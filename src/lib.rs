@@ -0,0 +1,41 @@
+//! Procedural road network generation, ported from CityGen.
+//!
+//! [`generate`] grows a network from a seed and a [`GenerationConfig`], and
+//! [`write_geojson`] exports the resulting segments for rendering.
+
+pub mod block;
+pub mod building;
+mod collision;
+pub mod config;
+pub mod error;
+pub mod export;
+pub mod generate;
+pub mod graph;
+pub(crate) mod math;
+pub mod network;
+pub mod obstacle;
+pub mod parcel;
+pub mod pattern;
+pub mod population;
+pub mod quad_tree;
+pub mod raster;
+pub mod road_class;
+pub mod segment;
+pub mod terrain;
+mod utility;
+pub mod validate;
+pub mod zoning;
+
+pub use geo;
+
+pub use collision::{CollisionLimits, CollisionObject};
+pub use config::GenerationConfig;
+pub use error::CitygenError;
+pub use export::{segments_to_geojson, write_geojson};
+pub use generate::{generate, GlobalConfig};
+pub use graph::RoadGraph;
+pub use population::PopulationMap;
+pub use quad_tree::Quadtree;
+pub use road_class::RoadClass;
+pub use segment::{Road, Segment};
+pub use validate::{validate_links, LinkViolation};
@@ -1,63 +1,245 @@
-mod collision;
-mod config;
-mod generate;
-mod math;
-mod quad_tree;
-mod segment;
-mod utility;
+use clap::Parser;
+use std::{path::PathBuf, process};
 
-use geojson::{feature::Id, Feature, FeatureCollection, GeoJson, Geometry, Value};
-use serde_json::Map;
-use std::{fs::File, io::Write};
+use citygen_rs::{
+    block::extract_blocks,
+    export::{
+        segments_extent, write_blocks, write_buildings, write_parcels, write_population,
+        PopulationFormat,
+    },
+    network::load_network,
+    obstacle::{load_boundary, load_obstacles},
+    parcel::subdivide_blocks,
+    pattern::{load_pattern_regions, RegionPatterns},
+    population::RasterPopulation,
+    raster::Extent,
+    terrain::{NoiseElevation, RasterElevation},
+    write_geojson,
+    zoning::Zoning,
+    CitygenError, GenerationConfig, GlobalConfig,
+};
 
-use generate::generate;
+/// Generates a road network and writes it as GeoJSON.
+#[derive(Parser)]
+#[command(version, about)]
+struct Args {
+    /// Generation parameters as a .toml or .json file
+    #[arg(short, long)]
+    config: Option<PathBuf>,
+
+    /// Where to write the generated GeoJSON
+    #[arg(short, long, default_value = "./output.geojson")]
+    output: PathBuf,
+
+    /// Seed for the population noise and all random choices
+    #[arg(short, long, default_value_t = 42)]
+    seed: u32,
+
+    /// Maximum number of segments, overriding the config file
+    #[arg(short = 'l', long)]
+    segment_limit: Option<usize>,
+
+    /// Grayscale .png or .pgm image to use as the population density
+    #[arg(long, requires = "raster_extent")]
+    population_raster: Option<PathBuf>,
+
+    /// World-space extent of the population raster
+    #[arg(
+        long,
+        value_name = "MIN_X,MIN_Y,MAX_X,MAX_Y",
+        value_parser = parse_extent,
+        allow_hyphen_values = true
+    )]
+    raster_extent: Option<Extent>,
+
+    /// GeoJSON polygons roads must not enter, with a `kind` of `water` or `no_build`
+    #[arg(long)]
+    obstacles: Option<PathBuf>,
+
+    /// GeoJSON polygon, e.g. a municipal border, that roads are clipped to
+    #[arg(long)]
+    boundary: Option<PathBuf>,
+
+    /// Existing roads to extend, as GeoJSON lines with an optional `class` property
+    #[arg(long)]
+    network: Option<PathBuf>,
+
+    /// GeoJSON polygons with a `pattern` property, e.g. `grid`, overriding
+    /// the configured growth pattern inside them
+    #[arg(long)]
+    pattern_regions: Option<PathBuf>,
+
+    /// Grayscale .png or .pgm heightmap for slope-aware growth
+    #[arg(long, requires = "heightmap_extent", conflicts_with = "terrain_noise")]
+    heightmap: Option<PathBuf>,
+
+    /// World-space extent of the heightmap
+    #[arg(
+        long,
+        value_name = "MIN_X,MIN_Y,MAX_X,MAX_Y",
+        value_parser = parse_extent,
+        allow_hyphen_values = true
+    )]
+    heightmap_extent: Option<Extent>,
+
+    /// Elevations of the darkest and brightest heightmap pixels
+    #[arg(
+        long,
+        value_name = "MIN,MAX",
+        value_parser = parse_range,
+        default_value = "0,100",
+        allow_hyphen_values = true
+    )]
+    elevation_range: (f64, f64),
+
+    /// Generate hilly terrain from noise with this height difference instead
+    #[arg(long, value_name = "AMPLITUDE")]
+    terrain_noise: Option<f64>,
+
+    /// Typical distance between hills of the noise terrain
+    #[arg(long, default_value_t = 4000.0)]
+    terrain_wavelength: f64,
+
+    /// Also write the blocks enclosed by roads as GeoJSON polygons
+    #[arg(long)]
+    blocks_output: Option<PathBuf>,
+
+    /// Also write the blocks cut into parcels with road frontage as GeoJSON
+    /// polygons
+    #[arg(long)]
+    parcels_output: Option<PathBuf>,
+
+    /// Also write building footprints along the roads as GeoJSON polygons
+    #[arg(long)]
+    buildings_output: Option<PathBuf>,
+
+    /// Also write the sampled population density as .geojson, .pgm or .asc
+    #[arg(long)]
+    population_output: Option<PathBuf>,
+
+    /// Grid spacing of the population export, in world units
+    #[arg(long, default_value_t = 100.0)]
+    population_cell_size: f64,
+}
+
+fn parse_extent(value: &str) -> Result<Extent, String> {
+    let parts = value
+        .split(',')
+        .map(|part| part.trim().parse::<f64>())
+        .collect::<Result<Vec<f64>, _>>()
+        .map_err(|e| e.to_string())?;
+
+    match parts[..] {
+        [min_x, min_y, max_x, max_y] => Ok(Extent {
+            min_x,
+            min_y,
+            max_x,
+            max_y,
+        }),
+        _ => Err(format!(
+            "expected 4 comma-separated numbers, got {}",
+            parts.len()
+        )),
+    }
+}
+
+fn parse_range(value: &str) -> Result<(f64, f64), String> {
+    let (min, max) = value
+        .split_once(',')
+        .ok_or_else(|| "expected 2 comma-separated numbers".to_string())?;
+    let parse = |part: &str| part.trim().parse::<f64>().map_err(|e| e.to_string());
+
+    Ok((parse(min)?, parse(max)?))
+}
 
 pub fn main() {
-    let (segments, _) = generate();
-
-    let mut features = Vec::new();
-
-    for mut segment in segments.into_iter() {
-        let coordinates = vec![
-            vec![segment.r.start.x(), segment.r.start.y()],
-            vec![segment.r.end.x(), segment.r.end.y()],
-        ];
-        let line_string_geometry = Geometry::new(Value::LineString(coordinates));
-
-        let mut props = Map::new();
-        let dir_val = serde_json::Number::from_f64(segment.dir().unwrap());
-        props.insert("dir".to_string(), dir_val.into());
-
-        let severed_val = serde_json::Value::Bool(segment.q.severed);
-        props.insert("severed".to_string(), severed_val.into());
-
-        let highway_val = if segment.q.highway.is_some() {
-            serde_json::Value::Bool(segment.q.highway.unwrap())
-        } else {
-            serde_json::Value::Bool(false)
-        };
-        props.insert("highway".to_string(), highway_val.into());
-
-        let feature = Feature {
-            bbox: None,
-            geometry: Some(line_string_geometry),
-            id: Some(Id::Number(segment.id.into())),
-            properties: Some(props),
-            foreign_members: None,
-        };
-
-        features.push(feature);
-    }
-
-    let feature_collection = FeatureCollection {
-        bbox: None,
-        features,
-        foreign_members: None,
+    let args = Args::parse();
+
+    if let Err(e) = run(&args) {
+        eprintln!("error: {}", e);
+        process::exit(1);
+    }
+}
+
+fn run(args: &Args) -> Result<(), CitygenError> {
+    let mut config = match &args.config {
+        Some(path) => GenerationConfig::from_file(path)?,
+        None => GenerationConfig::default(),
     };
 
-    let serialized = GeoJson::from(feature_collection).to_string();
+    if let Some(limit) = args.segment_limit {
+        config.segment_count_limit = limit;
+    }
+
+    let mut global_config = GlobalConfig::new(args.seed, &config)?;
+
+    if let (Some(path), Some(extent)) = (&args.population_raster, args.raster_extent) {
+        global_config.population = Box::new(RasterPopulation::from_file(path, extent)?);
+    }
+
+    if let Some(path) = &args.obstacles {
+        global_config.obstacles = load_obstacles(path)?;
+    }
+
+    if let Some(path) = &args.network {
+        global_config.existing_roads = load_network(path)?;
+    }
+
+    if let Some(path) = &args.pattern_regions {
+        global_config.pattern = Box::new(RegionPatterns {
+            regions: load_pattern_regions(path)?,
+            default: config.growth_pattern,
+        });
+    }
+
+    if let Some(path) = &args.boundary {
+        global_config.boundary = Some(load_boundary(path)?);
+    }
+
+    if let (Some(path), Some(extent)) = (&args.heightmap, args.heightmap_extent) {
+        let (min, max) = args.elevation_range;
+        global_config.elevation = Some(Box::new(RasterElevation::from_file(
+            path, extent, min, max,
+        )?));
+    } else if let Some(amplitude) = args.terrain_noise {
+        global_config.elevation = Some(Box::new(NoiseElevation::new(
+            args.seed,
+            amplitude,
+            args.terrain_wavelength,
+        )));
+    }
+
+    let (segments, _) = global_config.generate_main()?;
+
+    if let Some(path) = &args.population_output {
+        write_population(
+            global_config.population.as_ref(),
+            segments_extent(&segments, args.population_cell_size)?,
+            args.population_cell_size,
+            PopulationFormat::from_path(path)?,
+            path,
+        )?;
+    }
+
+    if args.blocks_output.is_some() || args.parcels_output.is_some() {
+        let zoning = Zoning::new(&segments, global_config.population.as_ref(), &config.zoning);
+        let mut blocks = extract_blocks(&segments);
+        zoning.zone_blocks(&mut blocks);
+
+        if let Some(path) = &args.blocks_output {
+            write_blocks(&blocks, path)?;
+        }
+
+        if let Some(path) = &args.parcels_output {
+            let mut parcels = subdivide_blocks(&blocks, &config);
+            zoning.zone_parcels(&mut parcels);
+            write_parcels(&parcels, path)?;
+        }
+    }
+
+    if let Some(path) = &args.buildings_output {
+        write_buildings(&global_config.place_buildings(), path)?;
+    }
 
-    // Write the JSON string to a GeoJSON file
-    let mut file = File::create("./output.geojson").unwrap();
-    file.write_all(serialized.as_bytes()).unwrap();
+    write_geojson(&segments, &args.output)
 }
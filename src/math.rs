@@ -1,8 +1,8 @@
 use std::f64::consts::PI;
 
 use super::{config::EPSILON, segment::Road};
-use geo::Point;
-use rand::Rng;
+use geo::{Point, Polygon};
+use rand::{rngs::StdRng, Rng};
 
 pub struct DotProduct {
     pub dot_product: f64,
@@ -16,8 +16,7 @@ pub struct AddedPoint {
     pub length2: f64,
 }
 
-pub fn random_range(min: f64, max: f64) -> f64 {
-    let mut rng = rand::thread_rng();
+pub fn random_range(rng: &mut StdRng, min: f64, max: f64) -> f64 {
     rng.gen_range(min..max)
 }
 
@@ -25,7 +24,7 @@ pub fn calculate_direction(r: &Road) -> f64 {
     let vector = subtract_points(r.end, r.start);
     let _cross_product = cross_product(Point::new(0.0, 1.0), vector);
     let _angle_between = angle_between(Point::new(0.0, 1.0), vector);
-    -1.0 * sign(_cross_product) * _angle_between
+    -sign(_cross_product) * _angle_between
 }
 
 pub fn length(point1: Point<f64>, point2: Point<f64>) -> f64 {
@@ -149,7 +148,7 @@ pub fn do_line_segments_intersect(
     let t = cross_product(subtract_points(q, p), s) / denominator;
 
     let do_segments_intersect = if !omit_ends {
-        (t >= 0.0) && (t <= 1.0) && (u >= 0.0) && (u <= 1.0)
+        (0.0..=1.0).contains(&t) && (0.0..=1.0).contains(&u)
     } else {
         (t > 0.001) && (t < 1.0 - 0.001) && (u > 0.001) && (u < 1.0 - 0.001)
     };
@@ -164,3 +163,26 @@ pub fn do_line_segments_intersect(
 
     None
 }
+
+/// Positions along `start`→`end`, as fractions of its length, where the
+/// segment crosses the boundary of `polygon` (holes included), sorted from
+/// the start.
+pub fn polygon_crossings(start: Point<f64>, end: Point<f64>, polygon: &Polygon<f64>) -> Vec<f64> {
+    let mut crossings = Vec::new();
+
+    for ring in std::iter::once(polygon.exterior()).chain(polygon.interiors().iter()) {
+        for line in ring.lines() {
+            if let Some(intersection) =
+                do_line_segments_intersect(start, end, line.start.into(), line.end.into(), false)
+            {
+                crossings.push(intersection.t);
+            }
+        }
+    }
+
+    crossings.sort_by(f64::total_cmp);
+    // a crossing through a vertex is reported by both of its edges
+    crossings.dedup_by(|a, b| (*a - *b).abs() < EPSILON);
+
+    crossings
+}
@@ -0,0 +1,68 @@
+use std::path::Path;
+
+use geo::{Geometry, LineString, Point};
+
+use super::{
+    config::EPSILON,
+    error::Result,
+    math::length,
+    road_class::RoadClass,
+    segment::Road,
+    utility::{parse_error, read_features},
+};
+
+/// A road of an existing network that new growth connects to but never moves.
+#[derive(Debug, Clone, Copy)]
+pub struct ExistingRoad {
+    pub road: Road,
+    pub class: RoadClass,
+}
+
+/// Reads every `LineString` and `MultiLineString` feature of a GeoJSON file,
+/// splitting lines into one road per pair of consecutive coordinates.
+///
+/// The `class` property names the road class, such as `"arterial"`, so files
+/// written by [`write_geojson`](crate::write_geojson) can be read back.
+/// Without it, a `highway` property of `true` marks a motorway and anything
+/// else is a local street.
+pub fn load_network(path: &Path) -> Result<Vec<ExistingRoad>> {
+    let mut roads = Vec::new();
+
+    for feature in read_features(path)? {
+        let class = match feature.property("class") {
+            Some(class) => serde_json::from_value(class.clone())
+                .map_err(|e| parse_error(path, format!("invalid road class: {}", e)))?,
+            None => match feature.property("highway").and_then(|h| h.as_bool()) {
+                Some(true) => RoadClass::Motorway,
+                _ => RoadClass::Local,
+            },
+        };
+
+        let Some(geometry) = feature.geometry else {
+            continue;
+        };
+        let geometry =
+            Geometry::<f64>::try_from(geometry).map_err(|e| parse_error(path, format!("{}", e)))?;
+
+        let lines: Vec<LineString<f64>> = match geometry {
+            Geometry::LineString(line) => vec![line],
+            Geometry::MultiLineString(multi_line) => multi_line.0,
+            _ => continue,
+        };
+
+        for line in lines.iter() {
+            for pair in line.lines() {
+                let road = Road {
+                    start: Point::from(pair.start),
+                    end: Point::from(pair.end),
+                };
+
+                if length(road.start, road.end) > EPSILON {
+                    roads.push(ExistingRoad { road, class });
+                }
+            }
+        }
+    }
+
+    Ok(roads)
+}
@@ -0,0 +1,84 @@
+use std::path::Path;
+
+use geo::{BoundingRect, Polygon, Rect};
+
+use super::{
+    error::Result,
+    utility::{parse_error, read_polygons},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ObstacleKind {
+    /// Lakes and rivers. Highways may bridge them when narrow enough.
+    Water,
+    /// Parks and other areas no road may enter.
+    NoBuild,
+}
+
+/// An area that roads must not grow into.
+#[derive(Debug, Clone)]
+pub struct Obstacle {
+    pub kind: ObstacleKind,
+    pub polygon: Polygon<f64>,
+    pub bounds: Rect<f64>,
+}
+
+impl Obstacle {
+    pub fn new(kind: ObstacleKind, polygon: Polygon<f64>) -> Option<Self> {
+        let bounds = polygon.bounding_rect()?;
+
+        Some(Obstacle {
+            kind,
+            polygon,
+            bounds,
+        })
+    }
+}
+
+/// Reads every `Polygon` and `MultiPolygon` feature of a GeoJSON file.
+///
+/// The `kind` property selects `"water"` or `"no_build"`; features without
+/// it are treated as no-build zones.
+pub fn load_obstacles(path: &Path) -> Result<Vec<Obstacle>> {
+    let mut obstacles = Vec::new();
+
+    for (feature, polygons) in read_polygons(path)? {
+        let kind = match feature.property("kind").and_then(|kind| kind.as_str()) {
+            Some("water") => ObstacleKind::Water,
+            Some("no_build") | None => ObstacleKind::NoBuild,
+            Some(other) => {
+                return Err(parse_error(
+                    path,
+                    format!(
+                        "unknown obstacle kind `{}`, expected `water` or `no_build`",
+                        other
+                    ),
+                ))
+            }
+        };
+
+        obstacles.extend(
+            polygons
+                .into_iter()
+                .filter_map(|polygon| Obstacle::new(kind, polygon)),
+        );
+    }
+
+    Ok(obstacles)
+}
+
+/// Reads the single polygon of a GeoJSON file to use as the city boundary.
+pub fn load_boundary(path: &Path) -> Result<Polygon<f64>> {
+    let mut polygons: Vec<Polygon<f64>> = read_polygons(path)?
+        .into_iter()
+        .flat_map(|(_, polygons)| polygons)
+        .collect();
+
+    match polygons.len() {
+        1 => Ok(polygons.remove(0)),
+        count => Err(parse_error(
+            path,
+            format!("expected exactly one boundary polygon, found {}", count),
+        )),
+    }
+}
@@ -0,0 +1,334 @@
+use geo::{Area, Contains, Coord, Line, LineString, Point, Polygon};
+
+use super::{block::Block, config::GenerationConfig, math::*, zoning::Zone};
+
+/// Distance within which a parcel edge counts as lying on a road.
+const FRONTAGE_TOLERANCE: f64 = 0.01;
+
+/// Pieces below this area are numerical leftovers of a cut.
+const EPSILON_AREA: f64 = 0.001;
+
+/// A piece of a block with access to a road.
+#[derive(Debug, Clone)]
+pub struct Parcel {
+    pub polygon: Polygon<f64>,
+    pub area: f64,
+    /// Id of the segment the parcel fronts, the one it shares the longest
+    /// stretch of outline with.
+    pub segment: usize,
+    /// Index of the block the parcel was cut from.
+    pub block: usize,
+    /// Land use, once assigned by [`Zoning`](crate::zoning::Zoning).
+    pub zone: Option<Zone>,
+}
+
+/// Cuts every block into parcels of at most `parcel_max_area`.
+///
+/// A block is halved across its longest edge, and the halves are cut again
+/// until they are small enough. Pieces smaller than `parcel_min_area`, and
+/// pieces in the middle of large blocks that touch no road, are dropped.
+pub fn subdivide_blocks(blocks: &[Block], config: &GenerationConfig) -> Vec<Parcel> {
+    let mut parcels = Vec::new();
+
+    for (index, block) in blocks.iter().enumerate() {
+        let frontage: Vec<(Line<f64>, usize)> = std::iter::once(block.polygon.exterior())
+            .chain(block.polygon.interiors())
+            .flat_map(|ring| ring.lines())
+            .zip(block.segments.iter().copied())
+            .collect();
+
+        let mut pieces = Vec::new();
+        subdivide(block.polygon.clone(), config.parcel_max_area, &mut pieces);
+
+        for polygon in pieces {
+            let area = polygon.unsigned_area();
+            if area < config.parcel_min_area {
+                continue;
+            }
+
+            if let Some(segment) = fronted_segment(&polygon, &frontage) {
+                parcels.push(Parcel {
+                    polygon,
+                    area,
+                    segment,
+                    block: index,
+                    zone: None,
+                });
+            }
+        }
+    }
+
+    parcels
+}
+
+/// Halves `polygon` across the middle of its longest edge until every piece
+/// is at most `max_area`.
+fn subdivide(polygon: Polygon<f64>, max_area: f64, pieces: &mut Vec<Polygon<f64>>) {
+    let area = polygon.unsigned_area();
+    if area <= max_area {
+        pieces.push(polygon);
+        return;
+    }
+
+    let Some(longest) = polygon
+        .exterior()
+        .lines()
+        .max_by(|a, b| line_length(a).total_cmp(&line_length(b)))
+    else {
+        return;
+    };
+    let middle = Point::from((longest.start + longest.end) / 2.0);
+    let along = mult_v_scalar(Point::from(longest.delta()), 1.0 / line_length(&longest));
+
+    let halves: Vec<Polygon<f64>> = split(&polygon, middle, along)
+        .into_iter()
+        .filter(|half| half.unsigned_area() > EPSILON_AREA)
+        .collect();
+
+    // a cut that leaves the polygon whole would recurse forever
+    if halves.len() < 2 {
+        pieces.push(polygon);
+        return;
+    }
+
+    for half in halves {
+        subdivide(half, max_area, pieces);
+    }
+}
+
+/// Cuts `polygon` along the line through `middle` perpendicular to `along`.
+///
+/// The crossings of the outline and holes with the line are sorted along it
+/// and paired up, each pair bounding a chord inside the polygon. Walking the
+/// rings and jumping across every chord traces the pieces, so concave blocks
+/// may fall apart into more than two. Holes the line misses are handed to
+/// the piece around them.
+fn split(polygon: &Polygon<f64>, middle: Point, along: Point) -> Vec<Polygon<f64>> {
+    let across = Point::new(-along.y(), along.x());
+    let side = |point: Point| dot_product(subtract_points(point, middle), along);
+
+    // every ring with the crossings inserted, as (point, is crossing), and
+    // the index of the following node on the same ring
+    let mut nodes: Vec<(Point, bool)> = Vec::new();
+    let mut next: Vec<usize> = Vec::new();
+    for ring in std::iter::once(polygon.exterior()).chain(polygon.interiors()) {
+        let first = nodes.len();
+
+        for line in ring.lines() {
+            let (start, end) = (Point::from(line.start), Point::from(line.end));
+            let (d_start, d_end) = (side(start), side(end));
+            nodes.push((start, false));
+
+            if (d_start < 0.0) != (d_end < 0.0) {
+                let t = d_start / (d_start - d_end);
+                let crossing = add_points(start, mult_v_scalar(subtract_points(end, start), t));
+                nodes.push((crossing, true));
+            }
+        }
+
+        next.extend(first + 1..nodes.len());
+        next.push(first);
+    }
+
+    let mut crossings: Vec<usize> = (0..nodes.len()).filter(|i| nodes[*i].1).collect();
+    if crossings.len() < 2 || !crossings.len().is_multiple_of(2) {
+        return vec![polygon.clone()];
+    }
+    crossings.sort_by(|a, b| {
+        let position = |i: usize| dot_product(subtract_points(nodes[i].0, middle), across);
+        position(*a).total_cmp(&position(*b))
+    });
+
+    let mut partner = vec![None; nodes.len()];
+    for pair in crossings.chunks(2) {
+        partner[pair[0]] = Some(pair[1]);
+        partner[pair[1]] = Some(pair[0]);
+    }
+
+    let mut visited = vec![false; nodes.len()];
+    let mut pieces = Vec::new();
+    let mut holes = Vec::new();
+
+    for first in 0..nodes.len() {
+        if visited[first] || nodes[first].1 {
+            continue;
+        }
+
+        let mut outline: Vec<Coord<f64>> = Vec::new();
+        let mut current = first;
+        // bounded in case a self-touching outline pairs crossings badly
+        for _ in 0..2 * nodes.len() {
+            visited[current] = true;
+            outline.push(nodes[current].0.into());
+
+            if let Some(other) = partner[current] {
+                outline.push(nodes[other].0.into());
+                current = other;
+            }
+
+            current = next[current];
+            if current == first {
+                break;
+            }
+        }
+
+        let piece = Polygon::new(LineString::from(outline), Vec::new());
+        if piece.signed_area() < 0.0 {
+            holes.push(piece.exterior().clone());
+        } else {
+            pieces.push(piece);
+        }
+    }
+
+    for hole in holes {
+        let inside = Point::from(hole.0[0]);
+        if let Some(piece) = pieces.iter_mut().find(|piece| piece.contains(&inside)) {
+            piece.interiors_push(hole);
+        }
+    }
+
+    pieces
+}
+
+/// The segment sharing the longest stretch of outline with `polygon`, or
+/// `None` if it touches no road.
+fn fronted_segment(polygon: &Polygon<f64>, frontage: &[(Line<f64>, usize)]) -> Option<usize> {
+    let mut best: Option<(f64, usize)> = None;
+
+    let rings = std::iter::once(polygon.exterior()).chain(polygon.interiors());
+    for edge in rings.flat_map(|ring| ring.lines()) {
+        let (start, end) = (Point::from(edge.start), Point::from(edge.end));
+
+        for (road, segment) in frontage.iter() {
+            let (road_start, road_end) = (Point::from(road.start), Point::from(road.end));
+            if !on_line(start, road_start, road_end) || !on_line(end, road_start, road_end) {
+                continue;
+            }
+
+            let shared = length(start, end);
+            if best.is_none_or(|(longest, _)| shared > longest) {
+                best = Some((shared, *segment));
+            }
+        }
+    }
+
+    best.map(|(_, segment)| segment)
+}
+
+fn on_line(point: Point, start: Point, end: Point) -> bool {
+    let added_point = distance_to_line(point, start, end);
+
+    added_point.distance2 <= FRONTAGE_TOLERANCE * FRONTAGE_TOLERANCE
+        && added_point.line_proj_2 >= -FRONTAGE_TOLERANCE
+        && added_point.line_proj_2 <= added_point.length2 + FRONTAGE_TOLERANCE
+}
+
+fn line_length(line: &Line<f64>) -> f64 {
+    length(line.start.into(), line.end.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{block::extract_blocks, segment::CollisionMetaInfo, segment::Segment};
+
+    /// Roads around the outline through `corners`, numbered in order.
+    fn ring(corners: &[(f64, f64)]) -> Vec<Segment> {
+        (0..corners.len())
+            .map(|i| {
+                Segment::new(
+                    Point::from(corners[i]),
+                    Point::from(corners[(i + 1) % corners.len()]),
+                    &0.0,
+                    &CollisionMetaInfo::default(),
+                    &i,
+                    &GenerationConfig::default(),
+                )
+            })
+            .collect()
+    }
+
+    fn config(max_area: f64, min_area: f64) -> GenerationConfig {
+        GenerationConfig {
+            parcel_max_area: max_area,
+            parcel_min_area: min_area,
+            ..GenerationConfig::default()
+        }
+    }
+
+    /// Whether an edge of `polygon` lies along `segment`.
+    fn touches(polygon: &Polygon<f64>, segment: &Segment) -> bool {
+        polygon.exterior().lines().any(|edge| {
+            on_line(edge.start.into(), segment.r.start, segment.r.end)
+                && on_line(edge.end.into(), segment.r.start, segment.r.end)
+                && length(edge.start.into(), edge.end.into()) > FRONTAGE_TOLERANCE
+        })
+    }
+
+    fn subdivide_and_check(segments: &[Segment], config: &GenerationConfig) -> Vec<Parcel> {
+        let blocks = extract_blocks(segments);
+        assert_eq!(blocks.len(), 1);
+        let parcels = subdivide_blocks(&blocks, config);
+        assert!(!parcels.is_empty());
+
+        for parcel in parcels.iter() {
+            assert!(parcel.area <= config.parcel_max_area);
+            assert!(parcel.area >= config.parcel_min_area);
+            assert!((parcel.polygon.unsigned_area() - parcel.area).abs() < 1e-6);
+            assert!(blocks[0].polygon.contains(&parcel.polygon));
+            assert!(touches(&parcel.polygon, &segments[parcel.segment]));
+        }
+
+        parcels
+    }
+
+    #[test]
+    fn rectangle_is_cut_into_road_fronting_parcels() {
+        let segments = ring(&[(0.0, 0.0), (400.0, 0.0), (400.0, 100.0), (0.0, 100.0)]);
+        let parcels = subdivide_and_check(&segments, &config(6000.0, 1000.0));
+
+        // halved three times, every piece reaching both long roads
+        assert_eq!(parcels.len(), 8);
+        let total: f64 = parcels.iter().map(|parcel| parcel.area).sum();
+        assert!((total - 40000.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn l_shaped_block_is_cut_into_road_fronting_parcels() {
+        let segments = ring(&[
+            (0.0, 0.0),
+            (300.0, 0.0),
+            (300.0, 100.0),
+            (100.0, 100.0),
+            (100.0, 300.0),
+            (0.0, 300.0),
+        ]);
+        let parcels = subdivide_and_check(&segments, &config(6000.0, 1000.0));
+
+        // both arms are narrow enough for every piece to reach a road
+        let total: f64 = parcels.iter().map(|parcel| parcel.area).sum();
+        assert!((total - 50000.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn pieces_without_frontage_are_dropped() {
+        let segments = ring(&[(0.0, 0.0), (400.0, 0.0), (400.0, 400.0), (0.0, 400.0)]);
+        let parcels = subdivide_and_check(&segments, &config(12000.0, 1000.0));
+
+        // a four by four grid of 100 by 100 pieces, less the four in the
+        // middle
+        assert_eq!(parcels.len(), 12);
+        let middle = Polygon::new(
+            LineString::from(vec![
+                (100.0, 100.0),
+                (300.0, 100.0),
+                (300.0, 300.0),
+                (100.0, 300.0),
+            ]),
+            Vec::new(),
+        );
+        for parcel in parcels.iter() {
+            assert!(!middle.contains(&parcel.polygon));
+        }
+    }
+}
@@ -0,0 +1,144 @@
+use std::path::Path;
+
+use geo::{BoundingRect, Contains, Point, Polygon, Rect};
+use serde::Deserialize;
+
+use super::{
+    error::Result,
+    utility::{parse_error, read_polygons},
+};
+
+/// Global goal shaping the layout of normal roads. Major roads always grow
+/// organically.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GrowthPattern {
+    /// Roads continue straight and branch at random angles.
+    Organic,
+    /// Roads run along a rotated grid of square blocks.
+    Grid,
+    /// Roads follow rings around the nearest city centre or run as spokes
+    /// towards it.
+    Radial,
+}
+
+/// Decides which growth pattern applies where, e.g. a grid downtown and
+/// organic suburbs.
+pub trait PatternMap {
+    fn pattern_at(&self, x: f64, y: f64) -> GrowthPattern;
+
+    /// How far roads are pulled from their organic course onto the pattern,
+    /// from `0.0` to `1.0`. `None` uses the configured `pattern_blend`.
+    fn blend_at(&self, _x: f64, _y: f64) -> Option<f64> {
+        None
+    }
+}
+
+/// The same pattern everywhere.
+impl PatternMap for GrowthPattern {
+    fn pattern_at(&self, _x: f64, _y: f64) -> GrowthPattern {
+        *self
+    }
+}
+
+/// Pattern given by a user-supplied function of the world position, for
+/// instance derived from the population density.
+pub struct FnPattern<F>(pub F);
+
+impl<F> PatternMap for FnPattern<F>
+where
+    F: Fn(f64, f64) -> GrowthPattern,
+{
+    fn pattern_at(&self, x: f64, y: f64) -> GrowthPattern {
+        (self.0)(x, y)
+    }
+}
+
+/// A polygon with its own growth pattern.
+#[derive(Debug, Clone)]
+pub struct PatternRegion {
+    pub pattern: GrowthPattern,
+    /// Blend used inside this region instead of the configured one.
+    pub blend: Option<f64>,
+    pub polygon: Polygon<f64>,
+    pub bounds: Rect<f64>,
+}
+
+impl PatternRegion {
+    pub fn new(pattern: GrowthPattern, blend: Option<f64>, polygon: Polygon<f64>) -> Option<Self> {
+        let bounds = polygon.bounding_rect()?;
+
+        Some(PatternRegion {
+            pattern,
+            blend,
+            polygon,
+            bounds,
+        })
+    }
+}
+
+/// Patterns assigned by polygon. Where regions overlap the first one wins,
+/// and everywhere else uses `default`.
+#[derive(Debug, Clone)]
+pub struct RegionPatterns {
+    pub regions: Vec<PatternRegion>,
+    pub default: GrowthPattern,
+}
+
+impl RegionPatterns {
+    fn region_at(&self, x: f64, y: f64) -> Option<&PatternRegion> {
+        let point = Point::new(x, y);
+
+        self.regions
+            .iter()
+            .find(|region| region.bounds.contains(&point) && region.polygon.contains(&point))
+    }
+}
+
+impl PatternMap for RegionPatterns {
+    fn pattern_at(&self, x: f64, y: f64) -> GrowthPattern {
+        self.region_at(x, y)
+            .map_or(self.default, |region| region.pattern)
+    }
+
+    fn blend_at(&self, x: f64, y: f64) -> Option<f64> {
+        self.region_at(x, y).and_then(|region| region.blend)
+    }
+}
+
+/// Reads every `Polygon` and `MultiPolygon` feature of a GeoJSON file along
+/// with its `pattern` property, such as `"grid"`, and optional `blend`.
+pub fn load_pattern_regions(path: &Path) -> Result<Vec<PatternRegion>> {
+    let mut regions = Vec::new();
+
+    for (feature, polygons) in read_polygons(path)? {
+        let Some(pattern) = feature.property("pattern") else {
+            return Err(parse_error(
+                path,
+                "every region needs a `pattern` property".into(),
+            ));
+        };
+        let pattern: GrowthPattern = serde_json::from_value(pattern.clone())
+            .map_err(|e| parse_error(path, format!("invalid pattern: {}", e)))?;
+
+        let blend = feature.property("blend").map(|blend| blend.as_f64());
+        let blend = match blend {
+            None => None,
+            Some(Some(blend)) if (0.0..=1.0).contains(&blend) => Some(blend),
+            Some(_) => {
+                return Err(parse_error(
+                    path,
+                    "`blend` must be a number between 0 and 1".into(),
+                ))
+            }
+        };
+
+        regions.extend(
+            polygons
+                .into_iter()
+                .filter_map(|polygon| PatternRegion::new(pattern, blend, polygon)),
+        );
+    }
+
+    Ok(regions)
+}
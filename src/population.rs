@@ -0,0 +1,147 @@
+use std::path::Path;
+
+use geo::Point;
+use noise::{NoiseFn, Simplex};
+
+use super::{
+    error::Result,
+    math::length,
+    raster::{Extent, Raster},
+};
+
+/// Population density consulted by the generator when deciding whether and
+/// where roads grow. Values are expected to lie roughly in `0.0..=1.0`.
+pub trait PopulationMap {
+    fn population_at(&self, x: f64, y: f64) -> f64;
+}
+
+/// The original CityGen density: three octaves of simplex noise.
+pub struct SimplexPopulation {
+    simplex: Simplex,
+}
+
+impl SimplexPopulation {
+    pub fn new(seed: u32) -> Self {
+        SimplexPopulation {
+            simplex: Simplex::new(seed),
+        }
+    }
+}
+
+impl PopulationMap for SimplexPopulation {
+    fn population_at(&self, x: f64, y: f64) -> f64 {
+        let value1 = (self.simplex.get([x / 10000.0, y / 10000.0]) + 1.0) / 2.0;
+        let value2 = (self.simplex.get([x / 20000.0 + 500.0, y / 20000.0 + 500.0]) + 1.0) / 2.0;
+        let value3 = (self
+            .simplex
+            .get([x / 20000.0 + 1000.0, y / 20000.0 + 1000.0])
+            + 1.0)
+            / 2.0;
+        f64::powf((value1 * value2 + value3) / 2.0, 2.0)
+    }
+}
+
+/// Density given by a user-supplied function of the world position.
+pub struct FnPopulation<F>(pub F);
+
+impl<F> PopulationMap for FnPopulation<F>
+where
+    F: Fn(f64, f64) -> f64,
+{
+    fn population_at(&self, x: f64, y: f64) -> f64 {
+        (self.0)(x, y)
+    }
+}
+
+/// Density read from a grayscale raster such as a census density map, with
+/// black as empty and white as the densest. Outside the raster there is no
+/// population.
+#[derive(Debug, Clone)]
+pub struct RasterPopulation {
+    pub raster: Raster,
+}
+
+impl RasterPopulation {
+    pub fn from_file(path: &Path, extent: Extent) -> Result<Self> {
+        Ok(RasterPopulation {
+            raster: Raster::from_file(path, extent)?,
+        })
+    }
+}
+
+impl PopulationMap for RasterPopulation {
+    fn population_at(&self, x: f64, y: f64) -> f64 {
+        self.raster.sample(x, y).unwrap_or(0.0)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct CityCentre {
+    pub position: Point<f64>,
+    /// Distance at which the density of this centre reaches zero.
+    pub radius: f64,
+    /// Density at the centre itself.
+    pub peak: f64,
+}
+
+/// Density falling off smoothly with the distance to the nearest of one or
+/// more city centres.
+#[derive(Debug, Clone)]
+pub struct RadialPopulation {
+    pub centres: Vec<CityCentre>,
+}
+
+impl PopulationMap for RadialPopulation {
+    fn population_at(&self, x: f64, y: f64) -> f64 {
+        let point = Point::new(x, y);
+
+        self.centres
+            .iter()
+            .map(|centre| {
+                let t = (length(centre.position, point) / centre.radius).min(1.0);
+                // smoothstep from the peak down to zero at the radius
+                centre.peak * (1.0 - t * t * (3.0 - 2.0 * t))
+            })
+            .fold(0.0, f64::max)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Combine {
+    /// Weighted sum of all maps.
+    Sum,
+    /// Product of all maps, each raised to its weight.
+    Product,
+    /// Largest weighted value.
+    Max,
+    /// Smallest weighted value.
+    Min,
+}
+
+/// Several population maps merged into one.
+pub struct CombinedPopulation {
+    pub maps: Vec<(Box<dyn PopulationMap>, f64)>,
+    pub mode: Combine,
+}
+
+impl PopulationMap for CombinedPopulation {
+    fn population_at(&self, x: f64, y: f64) -> f64 {
+        let values = self
+            .maps
+            .iter()
+            .map(|(map, weight)| (map.population_at(x, y), *weight));
+
+        match self.mode {
+            Combine::Sum => values.map(|(value, weight)| value * weight).sum(),
+            Combine::Product => values
+                .map(|(value, weight)| value.max(0.0).powf(weight))
+                .product(),
+            Combine::Max => values
+                .map(|(value, weight)| value * weight)
+                .fold(f64::NEG_INFINITY, f64::max),
+            Combine::Min => values
+                .map(|(value, weight)| value * weight)
+                .fold(f64::INFINITY, f64::min),
+        }
+    }
+}
@@ -1,34 +1,36 @@
+use std::slice;
+
 use super::collision::CollisionLimits;
 
+/// Spatial index of payloads by their bounding box, such as the positions of
+/// segments in `GlobalConfig::segments`.
 #[derive(Clone)]
-pub struct Quadtree {
+pub struct Quadtree<T> {
     max_objects: f64,
     max_levels: f64,
     level: f64,
     bounds: CollisionLimits,
-    objects: Vec<CollisionLimits>,
-    nodes: Vec<Quadtree>,
+    objects: Vec<(CollisionLimits, T)>,
+    nodes: Vec<Quadtree<T>>,
 }
 
-impl Quadtree {
-    pub fn from_config() -> Quadtree {
-        Quadtree {
-            max_objects: 10.0,
-            max_levels: 10.0,
-            level: 0.0,
-            bounds: CollisionLimits {
-                x: -20000.0,
-                y: -20000.0,
-                width: 40000.0,
-                height: 40000.0,
-                id: None,
-            },
-            objects: Vec::new(),
-            nodes: Vec::new(),
-        }
+impl<T> Quadtree<T> {
+    pub fn from_config() -> Quadtree<T> {
+        Quadtree::with_bounds(CollisionLimits {
+            x: -20000.0,
+            y: -20000.0,
+            width: 40000.0,
+            height: 40000.0,
+            id: None,
+        })
     }
 
-    fn new(bounds: CollisionLimits, max_objects: f64, max_levels: f64, level: f64) -> Quadtree {
+    /// An empty tree covering `bounds`, e.g. the extent of a city boundary.
+    pub fn with_bounds(bounds: CollisionLimits) -> Quadtree<T> {
+        Quadtree::new(bounds, 10.0, 10.0, 0.0)
+    }
+
+    fn new(bounds: CollisionLimits, max_objects: f64, max_levels: f64, level: f64) -> Quadtree<T> {
         Quadtree {
             max_objects,
             max_levels,
@@ -41,10 +43,10 @@ impl Quadtree {
 
     fn split(&mut self) {
         let next_level = self.level + 1.0;
-        let sub_width = (self.bounds.width / 2.0).round() as f64;
-        let sub_height = (self.bounds.height / 2.0).round() as f64;
-        let x = self.bounds.x.round() as f64;
-        let y = self.bounds.y.round() as f64;
+        let sub_width = (self.bounds.width / 2.0).round();
+        let sub_height = (self.bounds.height / 2.0).round();
+        let x = self.bounds.x.round();
+        let y = self.bounds.y.round();
 
         // Top right node
         self.nodes.push(Quadtree::new(
@@ -134,7 +136,7 @@ impl Quadtree {
         index
     }
 
-    pub fn insert(&mut self, p_rect: CollisionLimits) {
+    pub fn insert(&mut self, p_rect: CollisionLimits, payload: T) {
         let mut i = 0;
         let mut index: i64;
 
@@ -144,13 +146,13 @@ impl Quadtree {
 
             if index != -1 {
                 if let Some(node) = self.nodes.get_mut(index as usize) {
-                    node.insert(p_rect);
+                    node.insert(p_rect, payload);
                     return;
                 }
             }
         }
 
-        self.objects.push(p_rect);
+        self.objects.push((p_rect, payload));
 
         if self.objects.len() as f64 > self.max_objects && self.level < self.max_levels {
             // Split if we don't already have subnodes
@@ -160,12 +162,13 @@ impl Quadtree {
 
             // Add all objects to their corresponding subnodes
             while i < self.objects.len() {
-                let _obj = &self.objects.get(i as usize).unwrap();
+                let (_obj, _) = &self.objects[i];
                 index = self.get_index(_obj);
 
                 if index != -1 {
                     if let Some(node) = self.nodes.get_mut(index as usize) {
-                        node.insert(self.objects.remove(i as usize));
+                        let (p_rect, payload) = self.objects.remove(i);
+                        node.insert(p_rect, payload);
                     }
                 } else {
                     i += 1;
@@ -174,31 +177,52 @@ impl Quadtree {
         }
     }
 
-    pub fn retrieve(&self, p_rect: &CollisionLimits) -> Vec<CollisionLimits> {
-        let index = self.get_index(p_rect);
-        let mut return_objects = self.objects.clone(); // Assuming Bounds implements Clone
+    /// Payloads whose bounding box overlaps `p_rect`.
+    pub fn retrieve<'a>(&'a self, p_rect: &CollisionLimits) -> impl Iterator<Item = &'a T> + 'a {
+        let area = *p_rect;
+        self.candidates(p_rect)
+            .filter(move |(bounds, _)| bounds.overlaps(&area))
+            .map(|(_, payload)| payload)
+    }
 
-        // If we have subnodes
-        if self.nodes.as_slice().len() > 0 {
-            // If pRect fits into a subnode
-            if index != -1 {
-                return_objects.extend(self.nodes[index as usize].retrieve(p_rect).iter().cloned());
-            } else {
-                // If pRect does not fit into a subnode, check it against all subnodes
-                for node in &self.nodes {
-                    return_objects.extend(node.retrieve(p_rect).iter().cloned());
-                }
-            }
+    /// Every object stored in the nodes `p_rect` falls into, whether or not
+    /// its bounding box overlaps `p_rect`.
+    fn candidates(&self, p_rect: &CollisionLimits) -> Candidates<'_, T> {
+        Candidates {
+            area: *p_rect,
+            nodes: vec![self],
+            objects: [].iter(),
         }
-
-        return_objects
     }
+}
+
+/// Iterator over the objects found by [`Quadtree::candidates`].
+struct Candidates<'a, T> {
+    area: CollisionLimits,
+    nodes: Vec<&'a Quadtree<T>>,
+    objects: slice::Iter<'a, (CollisionLimits, T)>,
+}
 
-    fn clear(&mut self) {
-        self.objects.clear();
+impl<'a, T> Iterator for Candidates<'a, T> {
+    type Item = &'a (CollisionLimits, T);
 
-        for inner_node in &mut self.nodes {
-            inner_node.clear();
+    fn next(&mut self) -> Option<&'a (CollisionLimits, T)> {
+        loop {
+            if let Some(object) = self.objects.next() {
+                return Some(object);
+            }
+
+            let node = self.nodes.pop()?;
+            self.objects = node.objects.iter();
+
+            // If pRect fits into a subnode only check that one, otherwise
+            // check all subnodes in order
+            let index = node.get_index(&self.area);
+            if index != -1 {
+                self.nodes.extend(node.nodes.get(index as usize));
+            } else {
+                self.nodes.extend(node.nodes.iter().rev());
+            }
         }
     }
 }
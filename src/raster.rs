@@ -0,0 +1,301 @@
+use std::{fs, path::Path};
+
+use serde::Deserialize;
+
+use super::error::{CitygenError, Result};
+
+/// World-space rectangle covered by a raster.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+pub struct Extent {
+    pub min_x: f64,
+    pub min_y: f64,
+    pub max_x: f64,
+    pub max_y: f64,
+}
+
+impl Extent {
+    pub fn width(&self) -> f64 {
+        self.max_x - self.min_x
+    }
+
+    pub fn height(&self) -> f64 {
+        self.max_y - self.min_y
+    }
+
+    /// Whether the extent is finite, with each minimum below its maximum.
+    pub fn is_valid(&self) -> bool {
+        let (width, height) = (self.width(), self.height());
+
+        // infinite or NaN bounds make the width or height infinite or NaN
+        width.is_finite() && height.is_finite() && width > 0.0 && height > 0.0
+    }
+
+    pub fn contains(&self, x: f64, y: f64) -> bool {
+        (self.min_x..=self.max_x).contains(&x) && (self.min_y..=self.max_y).contains(&y)
+    }
+}
+
+/// A grayscale image stretched over a world-space extent, with values
+/// normalised to `0.0..=1.0`.
+///
+/// The first image row is the northern (`max_y`) edge, as in GIS rasters.
+#[derive(Debug, Clone)]
+pub struct Raster {
+    pub width: usize,
+    pub height: usize,
+    pub extent: Extent,
+    values: Vec<f64>,
+}
+
+impl Raster {
+    pub fn new(width: usize, height: usize, values: Vec<f64>, extent: Extent) -> Self {
+        assert_eq!(Some(values.len()), width.checked_mul(height));
+
+        Raster {
+            width,
+            height,
+            extent,
+            values,
+        }
+    }
+
+    /// Loads a `.png` or `.pgm` file.
+    pub fn from_file(path: &Path, extent: Extent) -> Result<Self> {
+        let invalid = |message: String| CitygenError::InvalidRaster {
+            path: path.to_path_buf(),
+            message,
+        };
+
+        if !extent.is_valid() {
+            return Err(invalid(format!(
+                "extent must be finite with each minimum below its maximum, got {:?}",
+                extent
+            )));
+        }
+
+        let bytes = fs::read(path)?;
+        let (width, height, values) = match path.extension().and_then(|ext| ext.to_str()) {
+            Some("png") => decode_png(&bytes).map_err(invalid)?,
+            Some("pgm") => decode_pgm(&bytes).map_err(invalid)?,
+            _ => return Err(invalid("expected a .png or .pgm file".into())),
+        };
+
+        if width == 0 || height == 0 {
+            return Err(invalid("image is empty".into()));
+        }
+
+        Ok(Raster::new(width, height, values, extent))
+    }
+
+    pub fn pixel(&self, column: usize, row: usize) -> f64 {
+        self.values[row * self.width + column]
+    }
+
+    /// Bilinearly interpolated value at a world position, or `None` outside
+    /// the extent.
+    pub fn sample(&self, x: f64, y: f64) -> Option<f64> {
+        if !self.extent.contains(x, y) {
+            return None;
+        }
+
+        // continuous pixel coordinates, with pixel centres at whole numbers
+        let u = (x - self.extent.min_x) / self.extent.width() * self.width as f64 - 0.5;
+        let v = (self.extent.max_y - y) / self.extent.height() * self.height as f64 - 0.5;
+
+        let u = u.clamp(0.0, (self.width - 1) as f64);
+        let v = v.clamp(0.0, (self.height - 1) as f64);
+
+        let (c0, r0) = (u.floor() as usize, v.floor() as usize);
+        let (c1, r1) = ((c0 + 1).min(self.width - 1), (r0 + 1).min(self.height - 1));
+        let (fu, fv) = (u - c0 as f64, v - r0 as f64);
+
+        let top = self.pixel(c0, r0) * (1.0 - fu) + self.pixel(c1, r0) * fu;
+        let bottom = self.pixel(c0, r1) * (1.0 - fu) + self.pixel(c1, r1) * fu;
+
+        Some(top * (1.0 - fv) + bottom * fv)
+    }
+}
+
+type Decoded = (usize, usize, Vec<f64>);
+
+fn decode_png(bytes: &[u8]) -> std::result::Result<Decoded, String> {
+    let mut decoder = png::Decoder::new(bytes);
+    // unpack palettes and sub-byte depths into whole bytes
+    decoder.set_transformations(png::Transformations::EXPAND);
+
+    let mut reader = decoder.read_info().map_err(|e| e.to_string())?;
+    let mut buf = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut buf).map_err(|e| e.to_string())?;
+
+    let channels = match info.color_type {
+        png::ColorType::Grayscale => 1,
+        png::ColorType::GrayscaleAlpha => 2,
+        other => return Err(format!("expected a grayscale image, got {:?}", other)),
+    };
+    let (width, height) = (info.width as usize, info.height as usize);
+
+    let values = match info.bit_depth {
+        png::BitDepth::Sixteen => buf[..info.buffer_size()]
+            .chunks_exact(2 * channels)
+            .map(|px| u16::from_be_bytes([px[0], px[1]]) as f64 / u16::MAX as f64)
+            .collect(),
+        _ => buf[..info.buffer_size()]
+            .chunks_exact(channels)
+            .map(|px| px[0] as f64 / u8::MAX as f64)
+            .collect(),
+    };
+
+    Ok((width, height, values))
+}
+
+/// Decodes both the plain (`P2`) and binary (`P5`) PGM variants.
+fn decode_pgm(bytes: &[u8]) -> std::result::Result<Decoded, String> {
+    let mut pos = 0;
+    let mut header = Vec::new();
+
+    // magic number, width, height and maxval, separated by whitespace and comments
+    while header.len() < 4 {
+        while pos < bytes.len() && bytes[pos].is_ascii_whitespace() {
+            pos += 1;
+        }
+        if pos < bytes.len() && bytes[pos] == b'#' {
+            while pos < bytes.len() && bytes[pos] != b'\n' {
+                pos += 1;
+            }
+            continue;
+        }
+
+        let start = pos;
+        while pos < bytes.len() && !bytes[pos].is_ascii_whitespace() {
+            pos += 1;
+        }
+        if start == pos {
+            return Err("truncated header".into());
+        }
+        header.push(String::from_utf8_lossy(&bytes[start..pos]).into_owned());
+    }
+
+    let parse = |field: &str, value: &str| {
+        value
+            .parse::<usize>()
+            .map_err(|_| format!("invalid {} `{}`", field, value))
+    };
+    let width = parse("width", &header[1])?;
+    let height = parse("height", &header[2])?;
+    let max_value = parse("maxval", &header[3])?;
+
+    if max_value == 0 || max_value > u16::MAX as usize {
+        return Err(format!(
+            "maxval must be between 1 and 65535, got {}",
+            max_value
+        ));
+    }
+
+    // every sample takes at least one byte, so a larger count cannot be
+    // backed by the file, whatever the header claims
+    let count = width
+        .checked_mul(height)
+        .filter(|count| *count <= bytes.len() - pos)
+        .ok_or_else(|| format!("{} by {} image does not fit the file", width, height))?;
+    let samples: Vec<usize> = match header[0].as_str() {
+        "P2" => String::from_utf8_lossy(&bytes[pos..])
+            .split_ascii_whitespace()
+            .take(count)
+            .map(|value| parse("sample", value))
+            .collect::<std::result::Result<_, _>>()?,
+        "P5" => {
+            // exactly one whitespace byte separates the header from the data
+            let data = bytes.get(pos + 1..).unwrap_or_default();
+            if max_value < 256 {
+                data.iter().take(count).map(|b| *b as usize).collect()
+            } else {
+                data.chunks_exact(2)
+                    .take(count)
+                    .map(|px| u16::from_be_bytes([px[0], px[1]]) as usize)
+                    .collect()
+            }
+        }
+        other => return Err(format!("unsupported PGM magic number `{}`", other)),
+    };
+
+    if samples.len() < count {
+        return Err(format!(
+            "expected {} samples, found {}",
+            count,
+            samples.len()
+        ));
+    }
+
+    let values = samples
+        .into_iter()
+        .map(|value| (value.min(max_value)) as f64 / max_value as f64)
+        .collect();
+
+    Ok((width, height, values))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const UNIT: Extent = Extent {
+        min_x: 0.0,
+        min_y: 0.0,
+        max_x: 2.0,
+        max_y: 2.0,
+    };
+
+    #[test]
+    fn decodes_plain_pgm() {
+        let pgm = b"P2\n# a comment\n2 2\n4\n0 1\n2 4\n";
+
+        assert_eq!(decode_pgm(pgm).unwrap(), (2, 2, vec![0.0, 0.25, 0.5, 1.0]));
+    }
+
+    #[test]
+    fn decodes_binary_pgm() {
+        let mut pgm = b"P5\n2 1\n255\n".to_vec();
+        pgm.extend([0, 255]);
+
+        assert_eq!(decode_pgm(&pgm).unwrap(), (2, 1, vec![0.0, 1.0]));
+    }
+
+    #[test]
+    fn decodes_sixteen_bit_pgm() {
+        let mut pgm = b"P5\n2 1\n65535\n".to_vec();
+        pgm.extend([0x00, 0x00, 0xff, 0xff]);
+        assert_eq!(decode_pgm(&pgm).unwrap(), (2, 1, vec![0.0, 1.0]));
+
+        let pgm = b"P2\n1 1\n1000\n250\n";
+        assert_eq!(decode_pgm(pgm).unwrap(), (1, 1, vec![0.25]));
+    }
+
+    #[test]
+    fn rejects_dimensions_the_file_cannot_hold() {
+        assert!(decode_pgm(b"P5\n4294967296 4294967296\n255\n").is_err());
+        assert!(decode_pgm(b"P5\n100 100\n255\n\x00\x01").is_err());
+    }
+
+    #[test]
+    fn samples_pixel_centres_exactly() {
+        // rows run from north to south
+        let raster = Raster::new(2, 2, vec![0.0, 1.0, 0.5, 0.25], UNIT);
+
+        assert_eq!(raster.sample(0.5, 1.5), Some(0.0));
+        assert_eq!(raster.sample(1.5, 1.5), Some(1.0));
+        assert_eq!(raster.sample(0.5, 0.5), Some(0.5));
+        assert_eq!(raster.sample(1.5, 0.5), Some(0.25));
+    }
+
+    #[test]
+    fn samples_between_centres_bilinearly_and_clamps_at_edges() {
+        let raster = Raster::new(2, 2, vec![0.0, 1.0, 0.5, 0.25], UNIT);
+
+        assert_eq!(raster.sample(1.0, 1.5), Some(0.5));
+        assert_eq!(raster.sample(1.0, 1.0), Some(0.4375));
+        // outside the outer pixel centres the edge value holds
+        assert_eq!(raster.sample(0.0, 2.0), Some(0.0));
+        assert_eq!(raster.sample(2.0, 0.0), Some(0.25));
+        assert_eq!(raster.sample(2.5, 1.0), None);
+    }
+}
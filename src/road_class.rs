@@ -0,0 +1,129 @@
+use serde::{Deserialize, Deserializer};
+
+/// Position of a road in the network hierarchy, from the largest to the
+/// smallest.
+///
+/// Every segment end may sprout side roads of its own class and of the next
+/// smaller one, so motorways give rise to arterials, arterials to collectors
+/// and so on down to alleys.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RoadClass {
+    Motorway,
+    Arterial,
+    Collector,
+    Local,
+    Alley,
+}
+
+impl RoadClass {
+    pub const ALL: [RoadClass; 5] = [
+        RoadClass::Motorway,
+        RoadClass::Arterial,
+        RoadClass::Collector,
+        RoadClass::Local,
+        RoadClass::Alley,
+    ];
+
+    /// Major roads grow like the original highways: they wander towards
+    /// denser population, may bridge water and ignore growth patterns.
+    pub fn is_major(&self) -> bool {
+        matches!(self, RoadClass::Motorway | RoadClass::Arterial)
+    }
+
+    /// The class of side roads one step down the hierarchy.
+    pub fn smaller(&self) -> Option<RoadClass> {
+        match self {
+            RoadClass::Motorway => Some(RoadClass::Arterial),
+            RoadClass::Arterial => Some(RoadClass::Collector),
+            RoadClass::Collector => Some(RoadClass::Local),
+            RoadClass::Local => Some(RoadClass::Alley),
+            RoadClass::Alley => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RoadClass::Motorway => "motorway",
+            RoadClass::Arterial => "arterial",
+            RoadClass::Collector => "collector",
+            RoadClass::Local => "local",
+            RoadClass::Alley => "alley",
+        }
+    }
+}
+
+/// Growth parameters of a single road class.
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RoadClassConfig {
+    pub segment_length: f64,
+    pub width: f64,
+    /// Chance per segment end of a side road of this class branching off a
+    /// road of this class or of the next bigger one.
+    pub branch_probability: f64,
+    /// Population a side road of this class needs to branch off at all.
+    pub branch_population_threshold: f64,
+    /// Steps a side road of this class waits before growing when it
+    /// branches off a bigger road.
+    pub time_delay: f64,
+}
+
+impl RoadClassConfig {
+    /// Defaults derived from the original CityGen highway and street values,
+    /// tuned so that motorways make up about the share of a default network
+    /// that highways did.
+    pub fn default_for(class: RoadClass) -> Self {
+        let (segment_length, width, branch_probability, branch_population_threshold, time_delay) =
+            match class {
+                RoadClass::Motorway => (400.0, 16.0, 0.05, 0.1, 0.0),
+                RoadClass::Arterial => (350.0, 12.0, 0.2, 0.1, 3.0),
+                RoadClass::Collector => (300.0, 8.0, 0.4, 0.1, 1.0),
+                RoadClass::Local => (300.0, 6.0, 0.4, 0.1, 1.0),
+                RoadClass::Alley => (150.0, 3.0, 0.1, 0.5, 2.0),
+            };
+
+        RoadClassConfig {
+            segment_length,
+            width,
+            branch_probability,
+            branch_population_threshold,
+            time_delay,
+        }
+    }
+}
+
+/// A class table in a config file, where left out fields keep the defaults.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RoadClassOverrides {
+    segment_length: Option<f64>,
+    width: Option<f64>,
+    branch_probability: Option<f64>,
+    branch_population_threshold: Option<f64>,
+    time_delay: Option<f64>,
+}
+
+/// Reads a possibly partial class table on top of the defaults of `class`.
+pub(crate) fn deserialize_class<'de, D>(
+    deserializer: D,
+    class: RoadClass,
+) -> Result<RoadClassConfig, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let overrides = RoadClassOverrides::deserialize(deserializer)?;
+    let defaults = RoadClassConfig::default_for(class);
+
+    Ok(RoadClassConfig {
+        segment_length: overrides.segment_length.unwrap_or(defaults.segment_length),
+        width: overrides.width.unwrap_or(defaults.width),
+        branch_probability: overrides
+            .branch_probability
+            .unwrap_or(defaults.branch_probability),
+        branch_population_threshold: overrides
+            .branch_population_threshold
+            .unwrap_or(defaults.branch_population_threshold),
+        time_delay: overrides.time_delay.unwrap_or(defaults.time_delay),
+    })
+}
@@ -1,15 +1,17 @@
 use std::f64::consts::PI;
 
 use geo::Point;
-// use rand::{rngs::StdRng, Rng, SeedableRng};
 
 use super::{
     collision::{CollisionObject, CollisionProperties, CollisionType},
     config::*,
+    error::Result,
     generate::GlobalConfig,
     math::*,
+    road_class::RoadClass,
 };
 
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum SegmentDirection {
     Back,
     Forward,
@@ -30,19 +32,28 @@ pub struct Link {
 
 #[derive(Clone, Copy)]
 pub struct CollisionMetaInfo {
-    pub highway: Option<bool>,
+    pub class: RoadClass,
     pub severed: bool,
+    /// The segment crosses water on a bridge.
+    pub bridge: bool,
 }
 
 impl CollisionMetaInfo {
-    pub fn new() -> Self {
+    pub fn new(class: RoadClass) -> Self {
         CollisionMetaInfo {
-            highway: Some(false),
+            class,
             severed: false,
+            bridge: false,
         }
     }
 }
 
+impl Default for CollisionMetaInfo {
+    fn default() -> Self {
+        Self::new(RoadClass::Local)
+    }
+}
+
 #[derive(Clone)]
 pub struct Segment {
     pub id: usize,
@@ -57,6 +68,8 @@ pub struct Segment {
     pub q: CollisionMetaInfo,
     pub links: Link,
     pub prev_segment_to_link: Option<usize>,
+    /// Ground height at the start and end, filled in once generation is done.
+    pub elevation: Option<[f64; 2]>,
 }
 
 impl PartialEq for Segment {
@@ -79,12 +92,15 @@ impl Segment {
         self.road_revision += 1;
     }
 
-    pub fn new(start: Point, end: Point, t: &f64, q: &CollisionMetaInfo, id: &usize) -> Self {
-        let width = if q.highway.is_some() {
-            HIGHWAY_SEGMENT_WIDTH
-        } else {
-            DEFAULT_SEGMENT_WIDTH
-        };
+    pub fn new(
+        start: Point,
+        end: Point,
+        t: &f64,
+        q: &CollisionMetaInfo,
+        id: &usize,
+        config: &GenerationConfig,
+    ) -> Self {
+        let width = config.road_class(q.class).width;
 
         let collision_properties = CollisionProperties {
             corners: Vec::new(),
@@ -97,8 +113,7 @@ impl Segment {
 
         let r = Road { start, end };
 
-        let collider =
-            CollisionObject::new(r, CollisionType::Line, collision_properties, id.clone());
+        let collider = CollisionObject::new(CollisionType::Line, collision_properties, *id);
 
         let links = Link {
             b: Vec::new(),
@@ -106,7 +121,7 @@ impl Segment {
         };
 
         Segment {
-            id: id.clone(),
+            id: *id,
             collider,
             road_revision: 0,
             dir_revision: 1,
@@ -118,9 +133,15 @@ impl Segment {
             q: *q,
             links,
             prev_segment_to_link: None,
+            elevation: None,
         }
     }
 
+    /// Width of the road surface, set by the road class.
+    pub fn width(&self) -> f64 {
+        self.collider.width()
+    }
+
     pub fn dir(&mut self) -> Option<f64> {
         if self.dir_revision != self.road_revision {
             self.dir_revision = self.road_revision;
@@ -138,21 +159,17 @@ impl Segment {
         self.cached_length
     }
 
-    pub fn start_is_backwards(&self, segments: &Vec<Segment>) -> bool {
-        let mut _direction: SegmentDirection;
-        let link_id: usize;
-
-        if self.links.b.len() > 0 {
-            _direction = SegmentDirection::Back;
-            link_id = self.links.b[0];
-        } else {
-            _direction = SegmentDirection::Forward;
-            link_id = self.links.f[0];
+    pub fn start_is_backwards(&self, global_config: &GlobalConfig) -> Result<bool> {
+        let (direction, link_id) = match (self.links.b.first(), self.links.f.first()) {
+            (Some(link_id), _) => (SegmentDirection::Back, *link_id),
+            (None, Some(link_id)) => (SegmentDirection::Forward, *link_id),
+            // nothing to be backwards relative to
+            (None, None) => return Ok(true),
         };
 
-        let link = segments.iter().find(|x| x.id == link_id).unwrap();
+        let link = global_config.segment(link_id)?;
 
-        match _direction {
+        Ok(match direction {
             SegmentDirection::Back => {
                 equal_v(link.r.start, self.r.start) || equal_v(link.r.end, self.r.start)
             }
@@ -160,14 +177,14 @@ impl Segment {
                 equal_v(link.r.start, self.r.end) || equal_v(link.r.end, self.r.end)
             }
             SegmentDirection::None => false,
-        }
+        })
     }
 
     pub fn links_for_end_containing(self, id: &usize) -> (Option<Vec<usize>>, SegmentDirection) {
         if self.links.b.contains(id) {
-            return (Some(self.links.b), SegmentDirection::Back);
+            (Some(self.links.b), SegmentDirection::Back)
         } else if self.links.f.contains(id) {
-            return (Some(self.links.f), SegmentDirection::Forward);
+            (Some(self.links.f), SegmentDirection::Forward)
         } else {
             (None, SegmentDirection::None)
         }
@@ -178,16 +195,25 @@ impl Segment {
         point: Point<f64>,
         segment: &mut Segment,
         global_config: &mut GlobalConfig,
-    ) {
-        let self_id: &usize = &self.id.clone();
-        let start_is_backwards = self.start_is_backwards(&global_config.segments);
+    ) -> Result<()> {
+        let self_id = self.id;
+        let start_is_backwards = self.start_is_backwards(global_config)?;
 
         global_config.last_id += 1;
-        let mut split_part = Segment::from_existing(self, None, None, None, &global_config.last_id);
-        let split_part_id = split_part.id.clone();
+        let mut split_part = Segment::from_existing(
+            self,
+            None,
+            None,
+            None,
+            &global_config.last_id,
+            &global_config.config,
+        );
+        let split_part_id = split_part.id;
 
         split_part.set_end(&point);
         self.set_start(&point);
+        split_part.q.bridge = global_config.crosses_water(&split_part.r);
+        self.q.bridge = global_config.crosses_water(&self.r);
 
         // links are not copied using the preceding factory method
         // copy link array for the split part, keeping references the same
@@ -200,15 +226,11 @@ impl Segment {
         };
 
         for link_id in fix_links.iter() {
-            let link: &mut Segment = global_config
-                .segments
-                .iter_mut()
-                .find(|x| &x.id == link_id)
-                .unwrap();
+            let link = global_config.segment_mut(*link_id)?;
 
-            if let Some(index) = link.links.b.iter().position(|x| x == self_id) {
+            if let Some(index) = link.links.b.iter().position(|x| *x == self_id) {
                 link.links.b[index] = split_part_id;
-            } else if let Some(index) = link.links.f.iter().position(|x| x == self_id) {
+            } else if let Some(index) = link.links.f.iter().position(|x| *x == self_id) {
                 link.links.f[index] = split_part_id;
             }
         }
@@ -231,8 +253,12 @@ impl Segment {
         segment.links.f.push(second_split.id);
         // - \\
 
-        global_config.quad_tree.insert(split_part.collider.limits());
-        global_config.segments.push(split_part);
+        global_config
+            .quad_tree
+            .insert(split_part.collider.limits(), global_config.segments().len());
+        global_config.push_segment(split_part);
+
+        Ok(())
     }
 
     pub fn from_existing(
@@ -241,12 +267,13 @@ impl Segment {
         r: Option<Road>,
         q: Option<CollisionMetaInfo>,
         id: &usize,
+        config: &GenerationConfig,
     ) -> Segment {
         let t = &t.unwrap_or(segment.t);
         let r = &r.unwrap_or(segment.r);
         let q = &q.unwrap_or(segment.q);
 
-        Segment::new(r.start, r.end, t, q, id)
+        Segment::new(r.start, r.end, t, q, id, config)
     }
 
     pub fn set_id(&mut self, id: usize) {
@@ -262,16 +289,17 @@ impl Segment {
         t: &f64,
         q: CollisionMetaInfo,
         id: &usize,
+        config: &GenerationConfig,
     ) -> Segment {
         // default to east
         let dir = dir.unwrap_or(&90.0);
-        let length = length.unwrap_or(&DEFAULT_SEGMENT_LENGTH);
+        let length = length.unwrap_or(&config.road_class(q.class).segment_length);
 
         let end = Point::new(
             start.x() + length * f64::sin(*dir * PI / 180.0),
             start.y() + length * f64::cos(*dir * PI / 180.0),
         );
 
-        Segment::new(start.clone(), end, &t, &q, id)
+        Segment::new(*start, end, t, &q, id, config)
     }
 }
@@ -0,0 +1,76 @@
+use std::path::Path;
+
+use noise::{NoiseFn, Simplex};
+
+use super::{
+    error::Result,
+    raster::{Extent, Raster},
+};
+
+/// Ground height consulted by the generator to keep roads off steep slopes.
+pub trait ElevationMap {
+    fn elevation_at(&self, x: f64, y: f64) -> f64;
+}
+
+/// Rolling hills from a single octave of simplex noise.
+pub struct NoiseElevation {
+    simplex: Simplex,
+    /// Height difference between the lowest valley and the highest peak.
+    pub amplitude: f64,
+    /// Typical distance between neighbouring hills.
+    pub wavelength: f64,
+}
+
+impl NoiseElevation {
+    pub fn new(seed: u32, amplitude: f64, wavelength: f64) -> Self {
+        NoiseElevation {
+            // offset the seed so hills don't line up with the population noise
+            simplex: Simplex::new(seed.wrapping_add(1)),
+            amplitude,
+            wavelength,
+        }
+    }
+}
+
+impl ElevationMap for NoiseElevation {
+    fn elevation_at(&self, x: f64, y: f64) -> f64 {
+        let value = (self.simplex.get([x / self.wavelength, y / self.wavelength]) + 1.0) / 2.0;
+        value * self.amplitude
+    }
+}
+
+/// Heights read from a grayscale heightmap, with black at `min_elevation`
+/// and white at `max_elevation`. Outside the raster the nearest edge value
+/// is used.
+#[derive(Debug, Clone)]
+pub struct RasterElevation {
+    pub raster: Raster,
+    pub min_elevation: f64,
+    pub max_elevation: f64,
+}
+
+impl RasterElevation {
+    pub fn from_file(
+        path: &Path,
+        extent: Extent,
+        min_elevation: f64,
+        max_elevation: f64,
+    ) -> Result<Self> {
+        Ok(RasterElevation {
+            raster: Raster::from_file(path, extent)?,
+            min_elevation,
+            max_elevation,
+        })
+    }
+}
+
+impl ElevationMap for RasterElevation {
+    fn elevation_at(&self, x: f64, y: f64) -> f64 {
+        let extent = self.raster.extent;
+        let x = x.clamp(extent.min_x, extent.max_x);
+        let y = y.clamp(extent.min_y, extent.max_y);
+        let value = self.raster.sample(x, y).unwrap_or(0.0);
+
+        self.min_elevation + value * (self.max_elevation - self.min_elevation)
+    }
+}
@@ -1,3 +1,10 @@
+use std::{fs, path::Path};
+
+use geo::{Geometry, Polygon};
+use geojson::{Feature, GeoJson};
+
+use super::error::{CitygenError, Result};
+
 pub fn extended_min<T, F>(collection: &[T], mut selector: F) -> Option<(T, usize)>
 where
     F: FnMut(&T) -> f64,
@@ -46,3 +53,50 @@ pub fn min_degree_difference(d1: f64, d2: f64) -> f64 {
     let diff = f64::abs(d1 - d2) % 180.0;
     f64::min(diff, f64::abs(diff - 180.0))
 }
+
+pub fn parse_error(path: &Path, message: String) -> CitygenError {
+    CitygenError::Parse {
+        path: path.to_path_buf(),
+        message,
+    }
+}
+
+/// Every feature of a GeoJSON `Feature` or `FeatureCollection` file.
+pub fn read_features(path: &Path) -> Result<Vec<Feature>> {
+    let contents = fs::read_to_string(path)?;
+    let geojson: GeoJson = contents
+        .parse()
+        .map_err(|e| parse_error(path, format!("{}", e)))?;
+
+    match geojson {
+        GeoJson::FeatureCollection(collection) => Ok(collection.features),
+        GeoJson::Feature(feature) => Ok(vec![feature]),
+        GeoJson::Geometry(_) => Err(parse_error(
+            path,
+            "expected a Feature or FeatureCollection".into(),
+        )),
+    }
+}
+
+/// The polygons of every feature, skipping features with other geometries.
+pub fn read_polygons(path: &Path) -> Result<Vec<(Feature, Vec<Polygon<f64>>)>> {
+    let mut result = Vec::new();
+
+    for mut feature in read_features(path)? {
+        let Some(geometry) = feature.geometry.take() else {
+            continue;
+        };
+        let geometry =
+            Geometry::<f64>::try_from(geometry).map_err(|e| parse_error(path, format!("{}", e)))?;
+
+        let polygons = match geometry {
+            Geometry::Polygon(polygon) => vec![polygon],
+            Geometry::MultiPolygon(multi_polygon) => multi_polygon.0,
+            _ => continue,
+        };
+
+        result.push((feature, polygons));
+    }
+
+    Ok(result)
+}
@@ -0,0 +1,212 @@
+use std::{collections::HashMap, fmt};
+
+use super::{
+    math::equal_v,
+    segment::{Segment, SegmentDirection},
+};
+
+/// A single inconsistency in the `Link` lists of a road network.
+#[derive(Debug, Clone, PartialEq)]
+pub enum LinkViolation {
+    /// `segment` links to an id that is not in the network.
+    Dangling { segment: usize, link: usize },
+    /// `segment` links to `link`, but `link` does not link back.
+    Asymmetric { segment: usize, link: usize },
+    /// `segment` links to itself.
+    SelfLink { segment: usize },
+    /// `segment` and `link` are linked at ends that do not touch.
+    EndpointMismatch {
+        segment: usize,
+        link: usize,
+        end: SegmentDirection,
+    },
+}
+
+impl fmt::Display for LinkViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LinkViolation::Dangling { segment, link } => {
+                write!(f, "segment {} links to missing segment {}", segment, link)
+            }
+            LinkViolation::Asymmetric { segment, link } => write!(
+                f,
+                "segment {} links to segment {} but not the other way around",
+                segment, link
+            ),
+            LinkViolation::SelfLink { segment } => {
+                write!(f, "segment {} links to itself", segment)
+            }
+            LinkViolation::EndpointMismatch { segment, link, end } => write!(
+                f,
+                "segment {} links to segment {} at its {} end, but they do not share that endpoint",
+                segment,
+                link,
+                match end {
+                    SegmentDirection::Back => "start",
+                    _ => "end",
+                }
+            ),
+        }
+    }
+}
+
+/// Checks that every link is symmetric, points at an existing segment and
+/// joins two segments at a shared endpoint on the correct end.
+pub fn validate_links(segments: &[Segment]) -> Vec<LinkViolation> {
+    let by_id: HashMap<usize, &Segment> = segments.iter().map(|s| (s.id, s)).collect();
+    let mut violations = Vec::new();
+
+    for segment in segments.iter() {
+        let ends = [
+            (SegmentDirection::Back, &segment.links.b, segment.r.start),
+            (SegmentDirection::Forward, &segment.links.f, segment.r.end),
+        ];
+
+        for (end, links, point) in ends {
+            for link_id in links.iter() {
+                if *link_id == segment.id {
+                    violations.push(LinkViolation::SelfLink {
+                        segment: segment.id,
+                    });
+                    continue;
+                }
+
+                let Some(link) = by_id.get(link_id) else {
+                    violations.push(LinkViolation::Dangling {
+                        segment: segment.id,
+                        link: *link_id,
+                    });
+                    continue;
+                };
+
+                let link_point = if link.links.b.contains(&segment.id) {
+                    link.r.start
+                } else if link.links.f.contains(&segment.id) {
+                    link.r.end
+                } else {
+                    violations.push(LinkViolation::Asymmetric {
+                        segment: segment.id,
+                        link: *link_id,
+                    });
+                    continue;
+                };
+
+                if !equal_v(point, link_point) {
+                    violations.push(LinkViolation::EndpointMismatch {
+                        segment: segment.id,
+                        link: *link_id,
+                        end,
+                    });
+                }
+            }
+        }
+    }
+
+    violations
+}
+
+#[cfg(test)]
+mod tests {
+    use geo::Point;
+
+    use super::*;
+    use crate::{
+        config::GenerationConfig,
+        generate::generate,
+        segment::{CollisionMetaInfo, Link},
+    };
+
+    fn segment(id: usize, start: (f64, f64), end: (f64, f64), b: &[usize], f: &[usize]) -> Segment {
+        let mut segment = Segment::new(
+            Point::from(start),
+            Point::from(end),
+            &0.0,
+            &CollisionMetaInfo::default(),
+            &id,
+            &GenerationConfig::default(),
+        );
+        segment.links = Link {
+            b: b.to_vec(),
+            f: f.to_vec(),
+        };
+        segment
+    }
+
+    /// Two segments joined end to start, with the given links at the joint.
+    fn pair(first_f: &[usize], second_b: &[usize]) -> Vec<Segment> {
+        vec![
+            segment(0, (0.0, 0.0), (0.0, 100.0), &[], first_f),
+            segment(1, (0.0, 100.0), (0.0, 200.0), second_b, &[]),
+        ]
+    }
+
+    #[test]
+    fn accepts_consistent_links() {
+        assert_eq!(validate_links(&pair(&[1], &[0])), Vec::new());
+    }
+
+    #[test]
+    fn reports_dangling_link() {
+        assert_eq!(
+            validate_links(&pair(&[1, 5], &[0])),
+            vec![LinkViolation::Dangling {
+                segment: 0,
+                link: 5
+            }]
+        );
+    }
+
+    #[test]
+    fn reports_asymmetric_link() {
+        assert_eq!(
+            validate_links(&pair(&[1], &[])),
+            vec![LinkViolation::Asymmetric {
+                segment: 0,
+                link: 1
+            }]
+        );
+    }
+
+    #[test]
+    fn reports_self_link() {
+        assert_eq!(
+            validate_links(&pair(&[0, 1], &[0])),
+            vec![LinkViolation::SelfLink { segment: 0 }]
+        );
+    }
+
+    #[test]
+    fn reports_endpoint_mismatch() {
+        let segments = vec![
+            segment(0, (0.0, 0.0), (0.0, 100.0), &[], &[1]),
+            segment(1, (50.0, 100.0), (50.0, 200.0), &[0], &[]),
+        ];
+
+        assert_eq!(
+            validate_links(&segments),
+            vec![
+                LinkViolation::EndpointMismatch {
+                    segment: 0,
+                    link: 1,
+                    end: SegmentDirection::Forward
+                },
+                LinkViolation::EndpointMismatch {
+                    segment: 1,
+                    link: 0,
+                    end: SegmentDirection::Back
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn generated_network_is_consistent() {
+        let config = GenerationConfig {
+            segment_count_limit: 300,
+            ..GenerationConfig::default()
+        };
+        let (segments, _) = generate(1, &config).unwrap();
+
+        assert_eq!(validate_links(&segments), Vec::new());
+    }
+}
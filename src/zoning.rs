@@ -0,0 +1,215 @@
+use geo::{Centroid, Point, Polygon};
+use serde::Deserialize;
+
+use super::{
+    block::Block,
+    math::*,
+    parcel::Parcel,
+    population::PopulationMap,
+    segment::{Road, Segment},
+};
+
+/// Land use of a block or parcel.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Zone {
+    Residential,
+    Commercial,
+    Industrial,
+    Park,
+}
+
+impl Zone {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Zone::Residential => "residential",
+            Zone::Commercial => "commercial",
+            Zone::Industrial => "industrial",
+            Zone::Park => "park",
+        }
+    }
+}
+
+/// Assigns `zone` to areas whose measurements all lie within the given
+/// bounds. Bounds left out do not restrict the rule.
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ZoneRule {
+    pub zone: Zone,
+    pub min_population: Option<f64>,
+    pub max_population: Option<f64>,
+    /// Distance to the nearest major road, i.e. motorway or arterial.
+    pub min_highway_distance: Option<f64>,
+    pub max_highway_distance: Option<f64>,
+    /// Distance to `ZoningConfig::centre`.
+    pub min_centre_distance: Option<f64>,
+    pub max_centre_distance: Option<f64>,
+}
+
+impl ZoneRule {
+    /// A rule giving `zone` to every area.
+    pub fn new(zone: Zone) -> Self {
+        ZoneRule {
+            zone,
+            min_population: None,
+            max_population: None,
+            min_highway_distance: None,
+            max_highway_distance: None,
+            min_centre_distance: None,
+            max_centre_distance: None,
+        }
+    }
+
+    fn matches(&self, population: f64, highway_distance: f64, centre_distance: f64) -> bool {
+        let within = |value: f64, min: Option<f64>, max: Option<f64>| {
+            min.is_none_or(|min| value >= min) && max.is_none_or(|max| value <= max)
+        };
+
+        within(population, self.min_population, self.max_population)
+            && within(
+                highway_distance,
+                self.min_highway_distance,
+                self.max_highway_distance,
+            )
+            && within(
+                centre_distance,
+                self.min_centre_distance,
+                self.max_centre_distance,
+            )
+    }
+
+    /// The `(name, min, max)` bounds of the rule, for validation.
+    pub(crate) fn bounds(&self) -> [(&'static str, Option<f64>, Option<f64>); 3] {
+        [
+            ("population", self.min_population, self.max_population),
+            (
+                "highway_distance",
+                self.min_highway_distance,
+                self.max_highway_distance,
+            ),
+            (
+                "centre_distance",
+                self.min_centre_distance,
+                self.max_centre_distance,
+            ),
+        ]
+    }
+}
+
+/// Rule table deciding the land use of blocks and parcels. Rules are tried
+/// in order and the first match wins.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ZoningConfig {
+    /// Point distances to the city centre are measured from.
+    pub centre: [f64; 2],
+    pub rules: Vec<ZoneRule>,
+    /// Zone of areas no rule matches.
+    pub default_zone: Zone,
+}
+
+impl Default for ZoningConfig {
+    fn default() -> Self {
+        ZoningConfig {
+            centre: [0.0, 0.0],
+            rules: vec![
+                // a dense downtown
+                ZoneRule {
+                    min_population: Some(0.18),
+                    max_centre_distance: Some(1500.0),
+                    ..ZoneRule::new(Zone::Commercial)
+                },
+                // shops lining the main roads
+                ZoneRule {
+                    min_population: Some(0.16),
+                    max_highway_distance: Some(60.0),
+                    ..ZoneRule::new(Zone::Commercial)
+                },
+                // sparsely populated land along the main roads outside town
+                ZoneRule {
+                    max_population: Some(0.15),
+                    max_highway_distance: Some(250.0),
+                    min_centre_distance: Some(1000.0),
+                    ..ZoneRule::new(Zone::Industrial)
+                },
+                ZoneRule {
+                    max_population: Some(0.12),
+                    ..ZoneRule::new(Zone::Park)
+                },
+            ],
+            default_zone: Zone::Residential,
+        }
+    }
+}
+
+/// Looks up zones for a finished network.
+pub struct Zoning<'a> {
+    config: &'a ZoningConfig,
+    population: &'a dyn PopulationMap,
+    major_roads: Vec<Road>,
+}
+
+impl<'a> Zoning<'a> {
+    pub fn new(
+        segments: &[Segment],
+        population: &'a dyn PopulationMap,
+        config: &'a ZoningConfig,
+    ) -> Self {
+        Zoning {
+            config,
+            population,
+            major_roads: segments
+                .iter()
+                .filter(|segment| segment.q.class.is_major())
+                .map(|segment| segment.r)
+                .collect(),
+        }
+    }
+
+    /// Zone of the area around `point`.
+    pub fn zone_at(&self, point: Point<f64>) -> Zone {
+        let population = self.population.population_at(point.x(), point.y());
+        let highway_distance = self
+            .major_roads
+            .iter()
+            .map(|road| distance_to_road(point, road))
+            .fold(f64::INFINITY, f64::min);
+        let (x, y) = (self.config.centre[0], self.config.centre[1]);
+        let centre_distance = length(point, Point::new(x, y));
+
+        self.config
+            .rules
+            .iter()
+            .find(|rule| rule.matches(population, highway_distance, centre_distance))
+            .map_or(self.config.default_zone, |rule| rule.zone)
+    }
+
+    /// Zone of a block or parcel, judged at its centroid.
+    pub fn zone_of(&self, polygon: &Polygon<f64>) -> Option<Zone> {
+        polygon.centroid().map(|centroid| self.zone_at(centroid))
+    }
+
+    pub fn zone_blocks(&self, blocks: &mut [Block]) {
+        for block in blocks.iter_mut() {
+            block.zone = self.zone_of(&block.polygon);
+        }
+    }
+
+    pub fn zone_parcels(&self, parcels: &mut [Parcel]) {
+        for parcel in parcels.iter_mut() {
+            parcel.zone = self.zone_of(&parcel.polygon);
+        }
+    }
+}
+
+fn distance_to_road(point: Point<f64>, road: &Road) -> f64 {
+    let added_point = distance_to_line(point, road.start, road.end);
+
+    if added_point.line_proj_2 <= 0.0 {
+        length(point, road.start)
+    } else if added_point.line_proj_2 >= added_point.length2 {
+        length(point, road.end)
+    } else {
+        added_point.distance2.sqrt()
+    }
+}